@@ -103,6 +103,14 @@ class Network {
     this.engine.emit('init');
   };
 
+  onTeleport = (event) => {
+    const {
+      json: { voxel },
+    } = event;
+
+    this.engine.player.teleport(voxel);
+  };
+
   onConfig = (event) => {
     const {
       json: { time, tickSpeed },
@@ -206,6 +214,11 @@ class Network {
         break;
       }
 
+      case 'TELEPORT': {
+        this.onTeleport(event);
+        break;
+      }
+
       case 'UPDATE': {
         this.onUpdate(event);
         break;
@@ -34,10 +34,66 @@ pub struct Block {
     pub is_plantable: bool,
     pub transparent_standalone: bool,
 
+    /// How many voxels a fluid flows horizontally away from its source
+    #[serde(default)]
+    pub fluid_range: u32,
+
+    #[serde(default)]
+    pub textures: HashMap<String, String>,
+
+    /// Growth stages of the block, stored in the voxel stage bits
+    #[serde(default)]
+    pub stages: Vec<BlockStage>,
+
+    /// Chance for a random tick to grow the block to its next stage
+    #[serde(default)]
+    pub growth_chance: f32,
+
+    /// Rules turning the block into another on random ticks, taking over its growth
+    #[serde(default)]
+    pub rules: Vec<BlockRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStage {
+    /// Textures of the stage, the block's own textures are used if empty
     #[serde(default)]
     pub textures: HashMap<String, String>,
 }
 
+/// A rule turning a block into another once all of its conditions are met
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRule {
+    /// Name of the block to turn into
+    pub into: String,
+
+    /// Chance for a random tick to apply the rule once its conditions are met
+    #[serde(default = "default_rule_chance")]
+    pub chance: f32,
+
+    /// Names of blocks of which one has to be in the 3x3x3 voxels around
+    #[serde(default)]
+    pub near: Vec<String>,
+
+    /// Minimum sunlight right above the block
+    #[serde(default)]
+    pub min_sunlight: Option<u32>,
+
+    /// Maximum sunlight right above the block
+    #[serde(default)]
+    pub max_sunlight: Option<u32>,
+
+    /// Whether the block has to be right under an opaque block, or not under one
+    #[serde(default)]
+    pub covered: Option<bool>,
+}
+
+fn default_rule_chance() -> f32 {
+    1.0
+}
+
 #[derive(Debug, Clone)]
 pub struct MeshType {
     pub positions: Vec<f32>,
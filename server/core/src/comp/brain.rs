@@ -44,6 +44,21 @@ pub struct BrainOptions {
     pub jump_force: f32,
     pub jump_time: f32, // ms
     pub air_jumps: u32,
+
+    /// Upward force applied while submerged deeper than `swim_depth`
+    #[serde(default = "default_swim_force")]
+    pub swim_force: f32,
+    /// Ratio of the body in fluid from which the entity starts swimming up
+    #[serde(default = "default_swim_depth")]
+    pub swim_depth: f32,
+}
+
+fn default_swim_force() -> f32 {
+    30.0
+}
+
+fn default_swim_depth() -> f32 {
+    0.6
 }
 
 impl Default for BrainOptions {
@@ -60,6 +75,9 @@ impl Default for BrainOptions {
             jump_force: 1.0,
             jump_time: 50.0,
             air_jumps: 0,
+
+            swim_force: default_swim_force(),
+            swim_depth: default_swim_depth(),
         }
     }
 }
@@ -104,6 +122,13 @@ impl Brain {
         self.state.jumping = false;
     }
 
+    /// Swim upward if the body is submerged, so entities don't sink in fluids
+    pub fn swim(&self, body: &mut RigidBody) {
+        if body.in_fluid && body.ratio_in_fluid > self.options.swim_depth {
+            body.apply_force(&Vec3(0.0, self.options.swim_force, 0.0));
+        }
+    }
+
     /// Operate brain state upon a rigid body
     pub fn operate(&mut self, target: &Vec3<f32>, body: &mut RigidBody, dt: f32) {
         // move implementation originally written as external module
@@ -111,6 +136,8 @@ impl Brain {
         //   for original code
         let origin = body.get_position();
 
+        self.swim(body);
+
         let dx = target.0 - origin.0;
         let dz = target.2 - origin.2;
 
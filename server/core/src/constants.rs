@@ -298,3 +298,9 @@ pub const CHUNK_HORIZONTAL_NEIGHBORS: [[i32; 2]; 4] = [[-1, 0], [1, 0], [0, -1],
 pub const DATA_PADDING: usize = 1;
 
 pub const WORLD_DATA_FILE: &str = "world.json";
+
+pub const PERMISSIONS_FILE: &str = "permissions.json";
+
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+pub const SCHEMATICS_FOLDER: &str = "schematics";
@@ -0,0 +1,326 @@
+use libflate::gzip::{Decoder, Encoder};
+
+use log::{error, info};
+
+use serde::Deserialize;
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const BACKUP_EXTENSION: &str = ".tar.gz";
+
+/// Where and how many backups to keep for a world
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupConfig {
+    /// Root folder of all backups, each world getting its own sub-folder
+    #[serde(default = "default_folder")]
+    pub folder: String,
+
+    /// Maximum amount of backups to keep per world, oldest ones are removed first
+    #[serde(default = "default_retention")]
+    pub retention: usize,
+}
+
+fn default_folder() -> String {
+    "backups".to_owned()
+}
+
+fn default_retention() -> usize {
+    10
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            folder: default_folder(),
+            retention: default_retention(),
+        }
+    }
+}
+
+/// Compressed point-in-time snapshots of a world's data folder
+/// (`WORLD_DATA_FILE`, the journal and its region files).
+///
+/// Backups are created from a staging copy of the world folder. Region files are only ever
+/// replaced through `RegionFile::write_atomic`'s rename, so they're staged as hard links that
+/// stay untouched while the world keeps running and being saved. Every other file, such as the
+/// journal that's appended to in place, is copied.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    pub folder: PathBuf,
+    pub world_name: String,
+    pub retention: usize,
+}
+
+impl Backups {
+    pub fn new(world_name: &str, config: &BackupConfig) -> Self {
+        let mut folder = PathBuf::from(&config.folder);
+        folder.push(world_name);
+
+        Self {
+            folder,
+            world_name: world_name.to_owned(),
+            retention: config.retention,
+        }
+    }
+
+    /// Create a backup of `world_folder` synchronously, returning the path to the backup.
+    ///
+    /// The world must not be written to while this runs, use `Backups::create_in_background`
+    /// for worlds that are currently running.
+    pub fn create(&self, world_folder: &Path) -> io::Result<PathBuf> {
+        let staging = self.stage(world_folder)?;
+        let result = self.archive(&staging);
+
+        fs::remove_dir_all(&staging)?;
+
+        result
+    }
+
+    /// Stage a consistent copy of `world_folder` and compress it on another thread.
+    ///
+    /// Make sure all pending saves are flushed before calling this. Returns the path the
+    /// backup will be written to.
+    pub fn create_in_background(&self, world_folder: &Path) -> io::Result<PathBuf> {
+        let staging = self.stage(world_folder)?;
+        let path = self.backup_path(&staging);
+        let backups = self.clone();
+
+        thread::Builder::new()
+            .name("world-backup".to_owned())
+            .spawn(move || {
+                match backups.archive(&staging) {
+                    Ok(path) => info!(
+                        "Backup of world \"{}\" written to {:?}.",
+                        backups.world_name, path
+                    ),
+                    Err(e) => error!("Backup of world \"{}\" failed: {}", backups.world_name, e),
+                }
+
+                let _ = fs::remove_dir_all(&staging);
+            })?;
+
+        Ok(path)
+    }
+
+    /// List all backups of this world, from oldest to newest
+    pub fn list(&self) -> io::Result<Vec<PathBuf>> {
+        if !self.folder.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut backups = fs::read_dir(&self.folder)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with(BACKUP_EXTENSION))
+            })
+            .collect::<Vec<_>>();
+
+        backups.sort();
+
+        Ok(backups)
+    }
+
+    /// Restore a backup into `world_folder`. The world must not be running.
+    ///
+    /// The current world folder is kept next to it, returning where it was moved to.
+    pub fn restore(&self, backup: &Path, world_folder: &Path) -> io::Result<Option<PathBuf>> {
+        let restoring = world_folder.with_extension("restoring");
+
+        if restoring.exists() {
+            fs::remove_dir_all(&restoring)?;
+        }
+
+        let decoder = Decoder::new(File::open(backup)?)?;
+        tar::Archive::new(decoder).unpack(&restoring)?;
+
+        let previous = if world_folder.exists() {
+            let previous = world_folder.with_extension(format!("before-restore-{}", timestamp()));
+            fs::rename(world_folder, &previous)?;
+            Some(previous)
+        } else {
+            None
+        };
+
+        fs::rename(&restoring, world_folder)?;
+
+        Ok(previous)
+    }
+
+    /// Remove the oldest backups exceeding `retention`, returning how many were removed
+    pub fn prune(&self) -> io::Result<usize> {
+        let backups = self.list()?;
+
+        if backups.len() <= self.retention {
+            return Ok(0);
+        }
+
+        let count = backups.len() - self.retention;
+
+        for backup in backups.iter().take(count) {
+            fs::remove_file(backup)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Mirror the world folder into a staging folder, hard linking region files where
+    /// supported and copying the rest.
+    fn stage(&self, world_folder: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.folder)?;
+
+        let mut staging = self.folder.clone();
+        staging.push(format!(".staging-{}-{}", self.world_name, timestamp()));
+
+        mirror(world_folder, &staging)?;
+
+        Ok(staging)
+    }
+
+    /// Compress a staging folder into a backup, then prune old backups
+    fn archive(&self, staging: &Path) -> io::Result<PathBuf> {
+        let path = self.backup_path(staging);
+        let tmp = path.with_extension("tmp");
+
+        let encoder = Encoder::new(File::create(&tmp)?)?;
+
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_dir_all(".", staging)?;
+
+        builder.into_inner()?.finish().into_result()?.sync_all()?;
+
+        fs::rename(&tmp, &path)?;
+
+        self.prune()?;
+
+        Ok(path)
+    }
+
+    /// The backup path of a staging folder, named after the staging timestamp
+    fn backup_path(&self, staging: &Path) -> PathBuf {
+        let name = staging.file_name().unwrap().to_str().unwrap();
+        let stamp = name.rsplit('-').next().unwrap();
+
+        let mut path = self.folder.clone();
+        path.push(format!("{}-{}{}", self.world_name, stamp, BACKUP_EXTENSION));
+        path
+    }
+}
+
+/// Milliseconds since the unix epoch, padded to sort correctly as a string
+fn timestamp() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+
+    format!("{:015}", millis)
+}
+
+/// Recursively copy every file from `from` into `to`, skipping temporary files. Region
+/// files are hard linked instead, falling back to a copy where linking isn't supported.
+fn mirror(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)?.flatten() {
+        let source = entry.path();
+
+        let mut target = to.to_path_buf();
+        target.push(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            mirror(&source, &target)?;
+            continue;
+        }
+
+        match source.extension().and_then(|e| e.to_str()) {
+            Some("tmp") => {}
+            Some("region") if fs::hard_link(&source, &target).is_ok() => {}
+            _ => {
+                fs::copy(&source, &target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_and_restore() {
+        let mut root = std::env::temp_dir();
+        root.push(format!("minejs-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut world = root.clone();
+        world.push("data");
+        world.push("test");
+
+        let mut chunks = world.clone();
+        chunks.push("chunks");
+        fs::create_dir_all(&chunks).unwrap();
+
+        fs::write(world.join("world.json"), "{\"time\":0}").unwrap();
+        fs::write(chunks.join("r_0_0.region"), vec![1, 2, 3]).unwrap();
+        fs::write(chunks.join("r_0_0.region.tmp"), vec![4]).unwrap();
+
+        let backups = Backups::new(
+            "test",
+            &BackupConfig {
+                folder: root.join("backups").to_str().unwrap().to_owned(),
+                retention: 2,
+            },
+        );
+
+        // files written in place are staged as copies
+        fs::write(world.join("journal.jsonl"), "{}\n").unwrap();
+
+        let staging = backups.stage(&world).unwrap();
+        fs::write(world.join("journal.jsonl"), "{}\n{}\n").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(staging.join("journal.jsonl")).unwrap(),
+            "{}\n"
+        );
+        fs::remove_dir_all(&staging).unwrap();
+
+        let first = backups.create(&world).unwrap();
+
+        fs::write(chunks.join("r_0_0.region"), vec![5, 6]).unwrap();
+
+        for _ in 0..2 {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            backups.create(&world).unwrap();
+        }
+
+        let list = backups.list().unwrap();
+        assert_eq!(list.len(), 2);
+        assert!(!list.contains(&first));
+
+        // the oldest backup got pruned, restore the next one
+        fs::write(chunks.join("r_0_0.region"), vec![7]).unwrap();
+
+        let previous = backups.restore(&list[0], &world).unwrap().unwrap();
+
+        assert_eq!(fs::read(chunks.join("r_0_0.region")).unwrap(), vec![5, 6]);
+        assert_eq!(
+            fs::read(previous.join("chunks/r_0_0.region")).unwrap(),
+            vec![7]
+        );
+        assert!(!chunks.join("r_0_0.region.tmp").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,15 +1,17 @@
 use byteorder::{ByteOrder, LittleEndian};
 
-use libflate::zlib::{Decoder, Encoder};
+use libflate::zlib::Decoder;
 
 use serde::{Deserialize, Serialize};
 
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 
 use std::{
-    fs::File,
-    io::{Read, Write},
-    path::Path,
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::gen::blocks::{BlockRotation, Blocks};
@@ -29,7 +31,10 @@ use server_utils::convert;
 
 use super::super::constants::DATA_PADDING;
 
-use super::chunks::MeshLevel;
+use super::{
+    chunks::MeshLevel,
+    region::{ChunkData, RegionFile},
+};
 
 /// Prototype for storing chunk's meshes and sending them to client
 #[derive(Debug, Clone)]
@@ -39,7 +44,7 @@ pub struct Meshes {
     pub transparent: Option<MeshType>,
 }
 
-/// Prototype for chunk's internal data used to send to client
+/// Legacy per-chunk JSON file, only read to migrate old worlds into region files
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ChunkFileData {
@@ -49,6 +54,29 @@ struct ChunkFileData {
     height_map: String,
 }
 
+/// Reasons a saved chunk could fail to load
+#[derive(Debug)]
+pub enum ChunkLoadError {
+    /// The region file containing the chunk couldn't be opened or has a broken header
+    Region(io::Error),
+    /// The chunk's data within its region file is malformed
+    Data(io::Error),
+    /// The legacy JSON chunk file is malformed
+    Legacy(String),
+}
+
+impl fmt::Display for ChunkLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkLoadError::Region(e) => write!(f, "unreadable region file: {}", e),
+            ChunkLoadError::Data(e) => write!(f, "malformed chunk data: {}", e),
+            ChunkLoadError::Legacy(e) => write!(f, "malformed legacy chunk file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChunkLoadError {}
+
 /// Base unit column for voxels
 ///
 /// Dimensions are specified as `max_height * chunk_size * max_height`
@@ -67,7 +95,11 @@ pub struct Chunk {
     pub min_inner: Vec3<i32>,
     pub max_inner: Vec3<i32>,
 
-    pub needs_saving: bool,
+    /// Bumped on every change to the chunk's saved data
+    pub generation: u64,
+    /// The generation last written to disk
+    pub saved_generation: u64,
+
     pub needs_propagation: bool,
     pub needs_terrain: bool,
     pub needs_decoration: bool,
@@ -82,12 +114,13 @@ pub struct Chunk {
 
     pub meshes: Vec<Meshes>,
 
-    pub file: String,
+    pub folder: PathBuf,
 }
 
 impl Chunk {
-    /// Constructor for a chunk. Attempts to load from existing files,
-    /// otherwise is marked to be generated.
+    /// Constructor for an empty chunk, marked to be generated.
+    ///
+    /// Use `Chunk::try_load` to load the chunk from its saved files.
     pub fn new(coords: Vec2<i32>, config: &WorldConfig, folder: &Path) -> Self {
         let Vec2(cx, cz) = coords;
 
@@ -95,7 +128,6 @@ impl Chunk {
             chunk_size: size,
             dimension,
             max_height,
-            save,
             ..
         } = config;
 
@@ -134,12 +166,7 @@ impl Chunk {
                 .add(&Vec3(0, max_height as i32, 0));
         let max = max_inner.add(&paddings);
 
-        let mut path = folder.to_path_buf();
-        let mut file_name = name.clone();
-        file_name.push_str(".json");
-        path.push(file_name.as_str());
-
-        let mut new_chunk = Self {
+        Self {
             name,
 
             coords,
@@ -152,7 +179,9 @@ impl Chunk {
             min_inner,
             max_inner,
 
-            needs_saving: false,
+            generation: 0,
+            saved_generation: 0,
+
             needs_propagation: true,
             needs_terrain: true,
             needs_decoration: true,
@@ -167,77 +196,197 @@ impl Chunk {
 
             meshes: Vec::new(),
 
-            file: path.into_os_string().into_string().unwrap(),
-        };
+            folder: folder.to_path_buf(),
+        }
+    }
+
+    /// Try to load the chunk from its region file, falling back to
+    /// the legacy JSON chunk file of older worlds.
+    ///
+    /// The chunk is left untouched if nothing is saved or loading fails.
+    pub fn try_load(&mut self) -> Result<(), ChunkLoadError> {
+        let region =
+            RegionFile::open(&self.folder, &self.coords, false).map_err(ChunkLoadError::Region)?;
 
-        if save {
-            new_chunk.try_load();
+        if let Some(mut region) = region {
+            if let Some(bytes) = region.read(&self.coords).map_err(ChunkLoadError::Data)? {
+                let data = ChunkData::decode(&bytes).map_err(ChunkLoadError::Data)?;
+
+                self.check_data(&data).map_err(ChunkLoadError::Data)?;
+                self.load_data(data);
+
+                return Ok(());
+            }
         }
 
-        new_chunk
-    }
-
-    /// Try to load the chunk from saved data file
-    pub fn try_load(&mut self) {
-        // open a file for reading
-
-        if let Ok(chunk_data) = File::open(&self.file) {
-            let data: ChunkFileData = serde_json::from_reader(chunk_data)
-                .unwrap_or_else(|_| panic!("Couldn't load chunk file: {:?}", self.coords));
-
-            let ChunkFileData {
-                needs_propagation,
-                voxels,
-                lights,
-                height_map,
-            } = data;
-
-            self.needs_saving = false;
-            self.needs_terrain = false;
-            self.needs_decoration = false;
-            self.needs_propagation = needs_propagation;
-
-            let decode_base64 = |base: String| {
-                let decoded = base64::decode(base).unwrap();
-                let mut decoder = Decoder::new(&decoded[..]).unwrap();
-                let mut buf = Vec::new();
-                decoder.read_to_end(&mut buf).unwrap();
-                let mut data = vec![0; buf.len() / 4];
-                LittleEndian::read_u32_into(&buf, &mut data);
-                data
-            };
-
-            self.lights.data = decode_base64(lights);
-            self.voxels.data = decode_base64(voxels);
-            self.height_map.data = decode_base64(height_map);
+        if let Some(data) = self.try_load_legacy()? {
+            self.check_data(&data)
+                .map_err(|e| ChunkLoadError::Legacy(e.to_string()))?;
+            self.load_data(data);
+
+            // migrate into the region file on the next save
+            self.mark_changed();
         }
+
+        Ok(())
     }
 
-    /// Save the chunk into a JSON compressed file
-    pub fn save(&self) {
-        let mut file = File::create(&self.file).expect("Could not create chunk file.");
+    /// Set the saved data that failed to load with `error` aside into `corrupt_folder`,
+    /// so that the chunk can be regenerated without losing the original data.
+    ///
+    /// Broken chunk data is copied out of its region, the entry itself gets replaced
+    /// once the regenerated chunk is saved. Every quarantined copy gets a name of its own, so
+    /// that a chunk failing again never overwrites an earlier copy. Unreadable regions hold
+    /// other chunks that may be fine, so they're never quarantined and this fails instead.
+    pub fn quarantine(&self, corrupt_folder: &Path, error: &ChunkLoadError) -> io::Result<()> {
+        fs::create_dir_all(corrupt_folder)?;
+
+        match error {
+            ChunkLoadError::Region(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unreadable regions aren't quarantined",
+            )),
+            ChunkLoadError::Data(_) => {
+                let mut region = RegionFile::open(&self.folder, &self.coords, false)?
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "region not found"))?;
+
+                // entries pointing outside of the region have nothing to copy
+                if let Ok(Some(bytes)) = region.read(&self.coords) {
+                    fs::write(unique_path(corrupt_folder, &self.name, "chunk"), bytes)?;
+                }
+
+                Ok(())
+            }
+            ChunkLoadError::Legacy(_) => fs::rename(
+                self.legacy_file(),
+                unique_path(corrupt_folder, &self.name, "json"),
+            ),
+        }
+    }
 
-        let to_base_64 = |data: &Vec<u32>| {
-            let mut bytes = vec![0; data.len() * 4];
-            LittleEndian::write_u32_into(data, &mut bytes);
+    /// Take a snapshot of the chunk's data to be saved
+    pub fn snapshot(&self) -> ChunkData {
+        ChunkData {
+            needs_propagation: self.needs_propagation,
+            voxels: self.voxels.data.to_owned(),
+            lights: self.lights.data.to_owned(),
+            height_map: self.height_map.data.to_owned(),
+        }
+    }
+
+    /// Save chunks into their region files synchronously, rewriting each region once through
+    /// `RegionFile::write_atomic`, then remove their legacy JSON files if any.
+    ///
+    /// Note: The server saves through `ChunkWriter` instead, this is meant for offline tasks.
+    pub fn save_all<'a>(chunks: impl IntoIterator<Item = &'a Chunk>) -> io::Result<()> {
+        let mut regions: HashMap<_, Vec<_>> = HashMap::new();
+        let mut legacy = vec![];
+
+        for chunk in chunks {
+            regions
+                .entry((
+                    chunk.folder.to_owned(),
+                    RegionFile::region_coords(&chunk.coords),
+                ))
+                .or_default()
+                .push((chunk.coords.to_owned(), chunk.snapshot().encode()?));
+
+            legacy.push(chunk.legacy_file());
+        }
+
+        for ((folder, region), updates) in regions {
+            RegionFile::write_atomic(&folder, &region, updates)?;
+        }
+
+        for path in legacy {
+            if path.exists() {
+                let _ = fs::remove_file(path);
+            }
+        }
 
-            let mut encoder = Encoder::new(vec![]).unwrap();
-            encoder.write_all(bytes.as_slice()).unwrap();
-            let encoded = encoder.finish().into_result().unwrap();
-            base64::encode(&encoded)
+        Ok(())
+    }
+
+    /// Path to the legacy `<x_z>.json` chunk file
+    pub fn legacy_file(&self) -> PathBuf {
+        let mut path = self.folder.to_path_buf();
+        path.push(format!("{}.json", self.name));
+        path
+    }
+
+    /// Read the legacy base64 JSON chunk file
+    fn try_load_legacy(&self) -> Result<Option<ChunkData>, ChunkLoadError> {
+        let chunk_data = match File::open(self.legacy_file()) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
         };
 
-        let data = ChunkFileData {
-            needs_propagation: self.needs_propagation,
-            lights: to_base_64(&self.lights.data),
-            voxels: to_base_64(&self.voxels.data),
-            height_map: to_base_64(&self.height_map.data),
+        let data: ChunkFileData = serde_json::from_reader(chunk_data)
+            .map_err(|e| ChunkLoadError::Legacy(e.to_string()))?;
+
+        let decode_base64 = |base: String| -> Result<Vec<u32>, ChunkLoadError> {
+            let decoded =
+                base64::decode(base).map_err(|e| ChunkLoadError::Legacy(e.to_string()))?;
+            let mut buf = Vec::new();
+            Decoder::new(&decoded[..])
+                .and_then(|mut decoder| decoder.read_to_end(&mut buf))
+                .map_err(|e| ChunkLoadError::Legacy(e.to_string()))?;
+            let mut data = vec![0; buf.len() / 4];
+            LittleEndian::read_u32_into(&buf[..data.len() * 4], &mut data);
+            Ok(data)
         };
 
-        let j = serde_json::to_string(&data).unwrap();
+        Ok(Some(ChunkData {
+            needs_propagation: data.needs_propagation,
+            voxels: decode_base64(data.voxels)?,
+            lights: decode_base64(data.lights)?,
+            height_map: decode_base64(data.height_map)?,
+        }))
+    }
+
+    /// Make sure loaded data matches the dimensions of this chunk
+    fn check_data(&self, data: &ChunkData) -> io::Result<()> {
+        if data.voxels.len() != self.voxels.data.len()
+            || data.lights.len() != self.lights.data.len()
+            || data.height_map.len() != self.height_map.data.len()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk data doesn't match the chunk dimensions",
+            ));
+        }
 
-        file.write_all(j.as_bytes())
-            .expect("Unable to write to chunk file.");
+        Ok(())
+    }
+
+    /// Apply loaded chunk data onto this chunk
+    pub fn load_data(&mut self, data: ChunkData) {
+        let ChunkData {
+            needs_propagation,
+            voxels,
+            lights,
+            height_map,
+        } = data;
+
+        self.needs_terrain = false;
+        self.needs_decoration = false;
+        self.needs_propagation = needs_propagation;
+
+        self.voxels.data = voxels;
+        self.lights.data = lights;
+        self.height_map.data = height_map;
+    }
+
+    /// Whether the chunk has changed since it was last saved
+    #[inline]
+    pub fn needs_saving(&self) -> bool {
+        self.generation != self.saved_generation
+    }
+
+    /// Bump the chunk's generation, marking it to be saved
+    #[inline]
+    pub fn mark_changed(&mut self) {
+        self.generation += 1;
     }
 
     /// Get the raw value of voxel
@@ -259,7 +408,12 @@ impl Chunk {
         assert!(self.contains(vx, vy, vz,));
 
         let Vec3(lx, ly, lz) = self.to_local(vx, vy, vz);
-        self.voxels[&[lx as usize, ly as usize, lz as usize]] = value;
+        let index = [lx as usize, ly as usize, lz as usize];
+
+        if self.voxels[&index] != value {
+            self.voxels[&index] = value;
+            self.mark_changed();
+        }
     }
 
     /// Get a voxel type within chunk by voxel coordinates
@@ -446,7 +600,12 @@ impl Chunk {
         assert!(self.contains(vx, 0, vz,));
 
         let Vec3(lx, _, lz) = self.to_local(vx, 0, vz);
-        self.height_map[&[lx as usize, lz as usize]] = height;
+        let index = [lx as usize, lz as usize];
+
+        if self.height_map[&index] != height {
+            self.height_map[&index] = height;
+            self.mark_changed();
+        }
     }
 
     /// Getter the entire voxel ndarray
@@ -459,6 +618,7 @@ impl Chunk {
     #[inline]
     pub fn set_voxels(&mut self, data: Ndarray<u32>) {
         self.voxels = data;
+        self.mark_changed();
     }
 
     /// Getter for the entire lights ndarray
@@ -471,6 +631,7 @@ impl Chunk {
     #[inline]
     pub fn set_lights(&mut self, data: Ndarray<u32>) {
         self.lights = data;
+        self.mark_changed();
     }
 
     /// Getter for the entire height map
@@ -483,6 +644,7 @@ impl Chunk {
     #[inline]
     pub fn set_height_map(&mut self, data: Ndarray<u32>) {
         self.height_map = data;
+        self.mark_changed();
     }
 
     /// Calculate and mark a sub-chunk as dirty at a certain height
@@ -547,7 +709,12 @@ impl Chunk {
     /// Set the red light value locally
     #[inline]
     fn set_local_red_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_red_light(self.lights[&[lx, ly, lz]], level);
+        self.set_local_light(
+            lx,
+            ly,
+            lz,
+            Lights::insert_red_light(self.lights[&[lx, ly, lz]], level),
+        );
     }
 
     /// Get the green light value locally
@@ -559,7 +726,12 @@ impl Chunk {
     /// Set the green light value locally
     #[inline]
     fn set_local_green_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_green_light(self.lights[&[lx, ly, lz]], level);
+        self.set_local_light(
+            lx,
+            ly,
+            lz,
+            Lights::insert_green_light(self.lights[&[lx, ly, lz]], level),
+        );
     }
 
     /// Get the blue light value locally
@@ -571,7 +743,12 @@ impl Chunk {
     /// Set the blue light value locally
     #[inline]
     fn set_local_blue_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_blue_light(self.lights[&[lx, ly, lz]], level);
+        self.set_local_light(
+            lx,
+            ly,
+            lz,
+            Lights::insert_blue_light(self.lights[&[lx, ly, lz]], level),
+        );
     }
 
     /// Get the sunlight value locally
@@ -583,7 +760,21 @@ impl Chunk {
     /// Set the sunlight value locally
     #[inline]
     fn set_local_sunlight(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_sunlight(self.lights[&[lx, ly, lz]], level);
+        self.set_local_light(
+            lx,
+            ly,
+            lz,
+            Lights::insert_sunlight(self.lights[&[lx, ly, lz]], level),
+        );
+    }
+
+    /// Set the raw light value locally, marking the chunk as changed if it differs
+    #[inline]
+    fn set_local_light(&mut self, lx: usize, ly: usize, lz: usize, value: u32) {
+        if self.lights[&[lx, ly, lz]] != value {
+            self.lights[&[lx, ly, lz]] = value;
+            self.mark_changed();
+        }
     }
 
     /// Convert voxel coordinates to local chunk coordinates
@@ -608,3 +799,19 @@ impl Chunk {
             && lz < size + DATA_PADDING as i32 * 2
     }
 }
+
+/// A path in `folder` for a file of `name`, stamped with the time and not taken yet
+pub(crate) fn unique_path(folder: &Path, name: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+
+    (0..)
+        .map(|i| match i {
+            0 => folder.join(format!("{}-{}.{}", name, millis, extension)),
+            i => folder.join(format!("{}-{}-{}.{}", name, millis, i, extension)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
@@ -2,18 +2,22 @@ use hashbrown::{HashMap, HashSet};
 use std::{collections::VecDeque, path::PathBuf, sync::Arc};
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use log::{debug, info};
+use log::{debug, error, info, warn};
 use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use serde::Deserialize;
 
-use crate::gen::{biomes::Biomes, blocks::BlockRotation};
+use crate::gen::{biomes::Biomes, blocks::BlockRotation, terrain::TerrainGenerator};
 
 use super::super::{
-    constants::{LEVEL_SEED, VOXEL_NEIGHBORS},
+    constants::VOXEL_NEIGHBORS,
     engine::{
-        chunk::{Chunk, Meshes},
+        chunk::{Chunk, ChunkLoadError, Meshes},
+        edits::VoxelEdit,
+        region::ChunkData,
         registry::Registry,
         space::Space,
         world::WorldConfig,
+        writer::{ChunkWriter, SaveReport},
     },
     gen::{
         builder::{Builder, VoxelUpdate},
@@ -21,6 +25,7 @@ use super::super::{
         lights::{LightColor, LightNode, Lights},
         mesher::Mesher,
     },
+    network::models::ChunkProtocol,
 };
 
 use server_common::{
@@ -37,33 +42,60 @@ pub enum MeshLevel {
     Levels(HashSet<u32>),
 }
 
+/// What to do when a saved chunk fails to load
+///
+/// Chunks of region files that can't be opened at all are always refused, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChunkLoadPolicy {
+    /// Generate the chunk again, overwriting the broken data on next save
+    Regenerate,
+    /// Move the broken data into the `corrupt/` folder, then regenerate
+    #[default]
+    Quarantine,
+    /// Leave the chunk unloaded and its data untouched
+    Refuse,
+}
+
+/// Chunks fresh out of terrain generation, each with the decorations sampled on its terrain
+type Generated = Vec<(Chunk, Vec<VoxelUpdate>)>;
+
 /// A wrapper around all the chunks
 #[derive(Debug)]
 pub struct Chunks {
     pub root_folder: PathBuf,
     pub chunk_folder: PathBuf,
+    pub corrupt_folder: PathBuf,
+    pub load_policy: ChunkLoadPolicy,
 
     pub chunk_cache: HashSet<Vec2<i32>>,
     pub to_generate: Vec<Chunk>,
     pub generating: HashSet<Vec2<i32>>,
     pub to_mesh: VecDeque<Vec2<i32>>,
     pub meshing: HashSet<Vec2<i32>>,
-    pub activities: VecDeque<Vec2<i32>>,
 
     pub config: Arc<WorldConfig>,
     pub registry: Arc<Registry>,
     pub builder: Arc<Builder>,
     pub biomes: Arc<Biomes>,
+    pub generator: Arc<dyn TerrainGenerator>,
 
     caching: bool,
+    writer: ChunkWriter,
+    saving: HashMap<u64, Vec<(Vec2<i32>, u64)>>,
+    queued: HashMap<Vec2<i32>, u64>,
     chunks: HashMap<Vec2<i32>, Chunk>,
+    refused: HashSet<Vec2<i32>>,
+    last_access: HashMap<Vec2<i32>, u64>,
+    access_counter: u64,
     update_queue: HashMap<Vec2<i32>, Vec<VoxelUpdate>>,
+    decorations: HashMap<Vec2<i32>, Vec<VoxelUpdate>>,
     noise: Noise,
 
     pool: ThreadPool,
 
-    gen_sender: Arc<Sender<Vec<Chunk>>>,
-    gen_receiver: Arc<Receiver<Vec<Chunk>>>,
+    gen_sender: Arc<Sender<Generated>>,
+    gen_receiver: Arc<Receiver<Generated>>,
 
     mesh_sender: Arc<Sender<Vec<Chunk>>>,
     mesh_receiver: Arc<Receiver<Vec<Chunk>>>,
@@ -74,7 +106,12 @@ pub struct Chunks {
  * NEED REFACTOR ASAP
  */
 impl Chunks {
-    pub fn new(world_name: &str, config: WorldConfig, registry: Registry) -> Self {
+    pub fn new(
+        world_name: &str,
+        config: WorldConfig,
+        registry: Registry,
+        generator: Arc<dyn TerrainGenerator>,
+    ) -> Self {
         let (gen_sender, gen_receiver) = unbounded();
         let gen_sender = Arc::new(gen_sender);
         let gen_receiver = Arc::new(gen_receiver);
@@ -89,6 +126,9 @@ impl Chunks {
         let mut chunk_folder = root_folder.clone();
         chunk_folder.push("chunks");
 
+        let mut corrupt_folder = root_folder.clone();
+        corrupt_folder.push("corrupt");
+
         if config.save {
             std::fs::create_dir_all(&chunk_folder).expect("Unable to create chunks directory...");
             info!(
@@ -99,26 +139,39 @@ impl Chunks {
             info!("World \"{}\" is temporarily saved in memory.", world_name);
         }
 
+        let writer = ChunkWriter::new(chunk_folder.clone());
+        let seed = config.seed;
+        let builder = Builder::new(registry.to_owned(), seed, &config.structures);
+
         Chunks {
             root_folder,
             chunk_folder,
+            corrupt_folder,
+            load_policy: config.chunk_load_policy,
             chunk_cache: HashSet::new(),
 
             config: Arc::new(config),
-            registry: Arc::new(registry.to_owned()),
-            builder: Arc::new(Builder::new(registry, Noise::new(LEVEL_SEED))),
-            biomes: Arc::new(Biomes::default()),
+            registry: Arc::new(registry),
+            builder: Arc::new(builder),
+            biomes: Arc::new(Biomes::new(seed)),
+            generator,
 
             to_generate: vec![],
             generating: HashSet::new(),
             to_mesh: VecDeque::new(),
             meshing: HashSet::new(),
-            activities: VecDeque::new(),
 
             caching: false,
+            writer,
+            saving: HashMap::new(),
+            queued: HashMap::new(),
             chunks: HashMap::new(),
+            refused: HashSet::new(),
+            last_access: HashMap::new(),
+            access_counter: 0,
             update_queue: HashMap::new(),
-            noise: Noise::new(LEVEL_SEED),
+            decorations: HashMap::new(),
+            noise: Noise::new(seed),
 
             pool: ThreadPoolBuilder::new()
                 .num_threads(num_cpus::get())
@@ -143,6 +196,7 @@ impl Chunks {
     /// are then sent to another thread to be meshed (lit and culled).
     /// 4. Checks if any thread is waiting to return a meshed chunk. If so, add
     /// them back into `chunks` itself.
+    /// 5. Collects the reports of finished background saves.
     pub fn tick(&mut self) {
         if !self.to_mesh.is_empty() {
             let to_mesh = self
@@ -173,7 +227,6 @@ impl Chunks {
                         if chunk.needs_propagation {
                             let lights = Lights::calc_light(&space, &registry, &config);
                             chunk.needs_propagation = false;
-                            chunk.needs_saving = true;
                             chunk.set_lights(lights);
                         }
 
@@ -216,14 +269,22 @@ impl Chunks {
             let config = Arc::clone(&self.config);
             let registry = Arc::clone(&self.registry);
             let biomes = Arc::clone(&self.biomes);
+            let builder = Arc::clone(&self.builder);
+            let generator = Arc::clone(&self.generator);
 
             self.pool.spawn(move || {
-                let chunks: Vec<Chunk> = chunks
+                let chunks = chunks
                     .into_iter()
                     .map(|mut chunk| {
-                        Generator::generate_chunk(&mut chunk, &registry, &biomes, &config);
-                        Generator::generate_chunk_height_map(&mut chunk, &registry, &config);
-                        chunk
+                        let decorations = Chunks::generate_terrain(
+                            &mut chunk,
+                            generator.as_ref(),
+                            &registry,
+                            &biomes,
+                            &builder,
+                            &config,
+                        );
+                        (chunk, decorations)
                     })
                     .collect();
                 sender.send(chunks).unwrap();
@@ -237,10 +298,16 @@ impl Chunks {
         }
 
         if let Ok(chunks) = self.gen_receiver.try_recv() {
-            chunks.into_iter().for_each(|c| {
-                self.add_chunk(c);
+            chunks.into_iter().for_each(|(c, decorations)| {
+                self.add_generated(c, decorations);
             });
         }
+
+        let reports = self.writer.poll();
+
+        reports.into_iter().for_each(|report| {
+            self.handle_save_report(report);
+        });
     }
 
     /// Getter for the count of internal chunks
@@ -303,6 +370,8 @@ impl Chunks {
             }
         }
 
+        self.update_activities(coords);
+
         self.get_chunk(coords)
     }
 
@@ -326,19 +395,139 @@ impl Chunks {
         self.chunk_cache.clear();
     }
 
-    /// Save all chunks to their according JSON files
-    pub fn save(&self) {
-        // saving the chunks
-        self.chunks.values().for_each(|chunk| {
-            if chunk.needs_saving {
-                chunk.save();
+    /// Drain the internal mutated chunks cache into chunk protocols to send to clients.
+    ///
+    /// Dirty sub-chunks are remeshed right away, followed by the lights of every cached chunk.
+    pub fn drain_cache(&mut self) -> Vec<ChunkProtocol> {
+        let cache = self.chunk_cache.drain().collect::<Vec<_>>();
+
+        let mut mesh_protocols = vec![];
+
+        cache.iter().for_each(|coords| {
+            let levels = match self.raw(coords) {
+                Some(chunk) => chunk.dirty_levels.clone(),
+                None => return,
+            };
+            let mesh_level = MeshLevel::Levels(levels);
+
+            if let Some(chunk) = self.get(coords, &mesh_level, true) {
+                mesh_protocols.push(chunk.get_protocol(true, false, false, mesh_level));
             }
-        })
+        });
+
+        let mut protocols = cache
+            .iter()
+            .filter_map(|coords| self.get_chunk(coords))
+            .map(|chunk| chunk.get_protocol(false, false, true, MeshLevel::None))
+            .collect::<Vec<_>>();
+
+        protocols.append(&mut mesh_protocols);
+        protocols
     }
 
-    /// Unload chunks when too many chunks are loaded.
-    pub fn unload(&mut self) {
-        todo!()
+    /// Snapshot all chunks changed since their last save and hand them to the background writer,
+    /// along with the unloaded chunks that failed to be written.
+    ///
+    /// Chunks already queued at their current generation are skipped. Returns the count of
+    /// chunks queued, the count actually persisted is reported once the writer is done.
+    pub fn save(&mut self) -> usize {
+        let queued = &self.queued;
+
+        let chunks = self
+            .chunks
+            .values()
+            .filter(|chunk| {
+                chunk.needs_saving()
+                    && queued
+                        .get(&chunk.coords)
+                        .is_none_or(|generation| *generation != chunk.generation)
+            })
+            .collect::<Vec<_>>();
+
+        let count = chunks.len();
+
+        let snapshots = chunks
+            .into_iter()
+            .map(|chunk| (chunk.coords.to_owned(), chunk.generation, chunk.snapshot()))
+            .collect();
+
+        self.submit(snapshots);
+        self.writer.retry();
+
+        count
+    }
+
+    /// Block until all queued saves are written to disk, returning the count of chunks persisted.
+    pub fn flush(&mut self) -> usize {
+        let reports = self.writer.flush();
+
+        reports
+            .into_iter()
+            .map(|report| self.handle_save_report(report))
+            .sum()
+    }
+
+    /// Unload the least recently accessed chunks until at most `max_loaded_chunks` are loaded.
+    ///
+    /// Chunks in `pinned`, or still being generated or meshed, are never unloaded. Chunks
+    /// that need saving are saved before being dropped. Returns the count of unloaded chunks.
+    ///
+    /// Note: Worlds that aren't saved lose the edits of the chunks they unload, which are
+    /// generated again once loaded back.
+    pub fn unload(&mut self, pinned: &HashSet<Vec2<i32>>) -> usize {
+        let max_loaded_chunks = self.config.max_loaded_chunks;
+
+        if self.chunks.len() <= max_loaded_chunks {
+            return 0;
+        }
+
+        let mut candidates = self
+            .chunks
+            .keys()
+            .filter(|coords| {
+                !pinned.contains(*coords)
+                    && !self.generating.contains(*coords)
+                    && !self.meshing.contains(*coords)
+                    && !self.to_mesh.contains(*coords)
+            })
+            .map(|coords| {
+                let access = self.last_access.get(coords).cloned().unwrap_or(0);
+                (access, coords.to_owned())
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_unstable_by_key(|(access, _)| *access);
+
+        let count = (self.chunks.len() - max_loaded_chunks).min(candidates.len());
+
+        let mut snapshots = vec![];
+
+        for (_, coords) in candidates.into_iter().take(count) {
+            self.last_access.remove(&coords);
+            self.chunk_cache.remove(&coords);
+
+            if let Some(chunk) = self.chunks.remove(&coords) {
+                if self.config.save && chunk.needs_saving() {
+                    snapshots.push((coords, chunk.generation, chunk.snapshot()));
+                }
+            }
+        }
+
+        self.submit(snapshots);
+
+        let chunks = &self.chunks;
+        self.decorations
+            .retain(|coords, _| chunks.contains_key(coords));
+
+        if count > 0 {
+            debug!(
+                "Unloaded {} chunks, {} remaining.",
+                count,
+                self.chunks.len()
+            );
+        }
+
+        count
     }
 
     /// Remesh a chunk, propagating itself and its neighbors then mesh.
@@ -436,20 +625,20 @@ impl Chunks {
                     let index = self.to_generate.iter().position(|c| c.coords.eq(&coords));
 
                     if index.is_none() {
-                        let mut new_chunk =
-                            Chunk::new(coords.to_owned(), &self.config, &self.chunk_folder);
-
-                        if let Some(updates) = self.update_queue.remove(&coords) {
-                            for u in updates {
-                                new_chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
-                            }
-                        }
+                        let mut new_chunk = match self.load_chunk(&coords) {
+                            Some(chunk) => chunk,
+                            None => continue,
+                        };
 
                         if new_chunk.needs_terrain {
                             if !self.generating.contains(&new_chunk.coords) {
                                 to_generate.push(new_chunk);
                             }
                         } else {
+                            if !new_chunk.needs_decoration {
+                                self.dequeue_updates(&mut new_chunk);
+                            }
+
                             self.add_chunk(new_chunk);
                         }
                     }
@@ -478,13 +667,23 @@ impl Chunks {
                 dist_a.partial_cmp(&dist_b).unwrap()
             })
         } else {
-            to_generate.par_iter_mut().for_each(|new_chunk| {
-                Generator::generate_chunk(new_chunk, &self.registry, &self.biomes, &self.config);
-                Generator::generate_chunk_height_map(new_chunk, &self.registry, &self.config);
-            });
+            let generated = to_generate
+                .into_par_iter()
+                .map(|mut new_chunk| {
+                    let decorations = Chunks::generate_terrain(
+                        &mut new_chunk,
+                        self.generator.as_ref(),
+                        &self.registry,
+                        &self.biomes,
+                        &self.builder,
+                        &self.config,
+                    );
+                    (new_chunk, decorations)
+                })
+                .collect::<Vec<_>>();
 
-            for chunk in to_generate {
-                self.add_chunk(chunk);
+            for (chunk, decorations) in generated {
+                self.add_generated(chunk, decorations);
             }
         }
 
@@ -494,36 +693,127 @@ impl Chunks {
             .flatten()
             .collect();
 
-        let builder = self.builder.clone();
-        let biomes = self.biomes.clone();
-
-        let to_decorate_updates: Vec<Vec<VoxelUpdate>> = to_decorate
-            .par_iter()
-            .map(|chunk| {
-                if !chunk.needs_decoration {
-                    return vec![];
-                }
+        // decorations are sampled on the terrain alone of every chunk they can reach from, so
+        // that neighbors agree on the voxels they share whichever is decorated first
+        let reach = self.builder.reach();
+
+        let mut sources = HashSet::new();
+        for chunk in to_decorate.iter() {
+            sources.extend(Generator::decoration_sources(
+                &chunk.coords,
+                reach,
+                &self.config,
+            ));
+        }
 
-                builder.build(chunk, &biomes)
+        // sources that weren't generated since they were last loaded get their terrain
+        // generated again
+        let missing = sources
+            .iter()
+            .filter(|coords| !self.decorations.contains_key(*coords))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let Chunks {
+            builder,
+            biomes,
+            generator,
+            registry,
+            config,
+            chunk_folder,
+            ..
+        } = &*self;
+
+        let regenerated = missing
+            .into_par_iter()
+            .map(|coords| {
+                let mut chunk = Chunk::new(coords.to_owned(), config, chunk_folder);
+                let decorations = Chunks::generate_terrain(
+                    &mut chunk,
+                    generator.as_ref(),
+                    registry,
+                    biomes,
+                    builder,
+                    config,
+                );
+                (coords, decorations)
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        let mut to_decorate_coords = Vec::new();
+        self.decorations.extend(regenerated);
 
         for mut chunk in to_decorate {
-            let coords = chunk.coords.to_owned();
+            if chunk.needs_decoration {
+                let updates = Generator::decoration_sources(&chunk.coords, reach, &self.config)
+                    .into_iter()
+                    .filter_map(|coords| self.decorations.get(&coords))
+                    .flatten();
+
+                Generator::decorate_chunk(
+                    &mut chunk,
+                    updates,
+                    |id| Generator::check_height(id, &self.registry),
+                    &self.config,
+                );
+            }
+
+            // edits that reached the chunk before it was decorated go on top of the decorations
             chunk.needs_decoration = false;
+            self.dequeue_updates(&mut chunk);
+            chunk.mark_changed();
             self.add_chunk(chunk);
-            to_decorate_coords.push(coords);
         }
 
-        for updates in to_decorate_updates.iter() {
+        // forget the decorations that no chunk within their reach is waiting on anymore
+        for coords in sources {
+            let done = Generator::decoration_sources(&coords, reach, &self.config)
+                .iter()
+                .all(|c| self.get_chunk(c).is_some_and(|c| !c.needs_decoration));
+
+            if done {
+                self.decorations.remove(&coords);
+            }
+        }
+    }
+
+    /// Generate the terrain and height map of a chunk, returning the decorations sampled on
+    /// that terrain alone if the chunk is to be decorated
+    fn generate_terrain(
+        chunk: &mut Chunk,
+        generator: &dyn TerrainGenerator,
+        registry: &Registry,
+        biomes: &Biomes,
+        builder: &Builder,
+        config: &WorldConfig,
+    ) -> Vec<VoxelUpdate> {
+        Generator::generate_chunk(chunk, generator, registry, biomes, config);
+        Generator::generate_chunk_height_map(chunk, registry, config);
+
+        if chunk.needs_decoration {
+            builder.build(chunk, biomes)
+        } else {
+            vec![]
+        }
+    }
+
+    /// Add a chunk fresh out of terrain generation, keeping the decorations sampled on its
+    /// terrain for itself and its neighbors to be decorated with
+    fn add_generated(&mut self, mut chunk: Chunk, decorations: Vec<VoxelUpdate>) {
+        if chunk.needs_decoration {
+            self.decorations
+                .insert(chunk.coords.to_owned(), decorations);
+        } else {
+            self.dequeue_updates(&mut chunk);
+        }
+
+        self.add_chunk(chunk);
+    }
+
+    /// Apply the updates queued for a chunk while it wasn't loaded
+    fn dequeue_updates(&mut self, chunk: &mut Chunk) {
+        if let Some(updates) = self.update_queue.remove(&chunk.coords) {
             for u in updates {
-                let h = self.get_max_height(u.voxel.0, u.voxel.2) as i32;
-                self.set_voxel_by_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
-                if u.voxel.1 > h && Generator::check_height(u.id, &self.registry) {
-                    self.set_max_height(u.voxel.0, u.voxel.2, u.voxel.1 as u32);
-                }
+                chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
             }
         }
     }
@@ -558,8 +848,6 @@ impl Chunks {
 
     /// Get a mutable chunk reference from a coordinate
     pub fn get_chunk_mut(&mut self, coords: &Vec2<i32>) -> Option<&mut Chunk> {
-        // self.update_activities(coords);
-
         let chunk = self.chunks.get_mut(&coords);
         // ? does non-mutable chunks need to be cached?
         if self.caching && chunk.is_some() {
@@ -650,6 +938,38 @@ impl Chunks {
         })
     }
 
+    /// Change a voxel's type and stage as part of the world's simulation, caching the mutated
+    /// chunks to be sent through `Chunks::drain_cache`. The rotation is kept if the type is.
+    ///
+    /// Returns false without changing anything if the voxel's chunk isn't ready for changes.
+    pub fn simulate_update(&mut self, voxel: &Vec3<i32>, id: u32, stage: u32) -> bool {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        if vy < 0
+            || vy >= self.config.max_height as i32
+            || self
+                .get_chunk_by_voxel(vx, vy, vz)
+                .is_none_or(|chunk| chunk.needs_propagation)
+        {
+            return false;
+        }
+
+        self.start_caching();
+
+        if self.get_voxel_by_voxel(vx, vy, vz) != id {
+            self.update(vx, vy, vz, id, 0, 0);
+        }
+
+        self.set_voxel_stage_by_voxel(vx, vy, vz, stage);
+
+        self.stop_caching();
+
+        let neighbor_chunks = self.get_neighbor_chunk_coords(vx, vy, vz);
+        self.chunk_cache.extend(neighbor_chunks);
+
+        true
+    }
+
     /// Get the voxel rotation at a voxel coordinate
     pub fn get_voxel_rotation_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> BlockRotation {
         let chunk = self.get_chunk_by_voxel(vx, vy, vz);
@@ -701,6 +1021,20 @@ impl Chunks {
         })
     }
 
+    /// Get the whole state of a voxel, as the edit that would restore it
+    pub fn get_voxel_edit(&self, vx: i32, vy: i32, vz: i32) -> VoxelEdit {
+        let (rotation, y_rotation) =
+            BlockRotation::decode(&self.get_voxel_rotation_by_voxel(vx, vy, vz));
+
+        VoxelEdit {
+            voxel: Vec3(vx, vy, vz),
+            id: self.get_voxel_by_voxel(vx, vy, vz),
+            rotation,
+            y_rotation,
+            stage: self.get_voxel_stage_by_voxel(vx, vy, vz),
+        }
+    }
+
     /// Get the voxel stage at a voxel coordinate
     pub fn get_voxel_stage_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
         let chunk = self.get_chunk_by_voxel(vx, vy, vz);
@@ -875,15 +1209,19 @@ impl Chunks {
         !block.is_solid || block.is_plant
     }
 
+    /// Get whether a rigid body can move through a voxel, which it also can through fluids
+    pub fn get_passable_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        self.get_walkable_by_voxel(vx, vy, vz) || self.get_fluidity_by_voxel(vx, vy, vz)
+    }
+
     /// Get whether a voxel is solid
     pub fn get_solidity_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
         self.get_voxel_by_voxel(vx, vy, vz) != 0
     }
 
     /// Get whether a voxel is fluid
-    pub fn get_fluidity_by_voxel(&self, _vx: i32, _vy: i32, _vz: i32) -> bool {
-        // TODO: ADD FLUIDS
-        false
+    pub fn get_fluidity_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        self.registry.is_fluid(self.get_voxel_by_voxel(vx, vy, vz))
     }
 
     /// Get neighboring chunks according to a voxel coordinate
@@ -950,6 +1288,32 @@ impl Chunks {
         voxel
     }
 
+    /// Load a chunk from disk if it isn't loaded yet, returning whether it's ready for changes.
+    ///
+    /// Chunks that aren't saved, or still need their terrain or decorations, are left to
+    /// `Chunks::generate`.
+    pub fn load_saved(&mut self, coords: &Vec2<i32>) -> bool {
+        if let Some(chunk) = self.get_chunk(coords) {
+            return !chunk.needs_propagation;
+        }
+
+        if !self.config.save || self.generating.contains(coords) {
+            return false;
+        }
+
+        let mut chunk = match self.load_chunk(coords) {
+            Some(chunk) if !chunk.needs_terrain && !chunk.needs_decoration => chunk,
+            _ => return false,
+        };
+
+        self.dequeue_updates(&mut chunk);
+
+        let ready = !chunk.needs_propagation;
+        self.add_chunk(chunk);
+
+        ready
+    }
+
     /// Add a chunk instance to self
     ///
     /// Removes existing chunks first.
@@ -961,16 +1325,10 @@ impl Chunks {
 
         self.chunks.remove(&chunk.coords);
         self.chunks.insert(chunk.coords.to_owned(), chunk);
-
-        self.unload_chunks();
     }
 
     /// Update a voxel to a new type
     pub fn update(&mut self, vx: i32, vy: i32, vz: i32, id: u32, rotation: u32, y_rotation: u32) {
-        // TODO: fix this code (might have better way)
-        self.get_chunk_by_voxel_mut(vx, vy, vz)
-            .unwrap()
-            .needs_saving = true;
         let needs_propagation = self
             .get_chunk_by_voxel(vx, vy, vz)
             .unwrap()
@@ -1183,20 +1541,6 @@ impl Chunks {
         }
     }
 
-    /// Mark a chunk for saving from a voxel coordinate
-    pub fn mark_saving_from_voxel(&mut self, vx: i32, vy: i32, vz: i32) {
-        self.get_chunk_by_voxel_mut(vx, vy, vz)
-            .unwrap()
-            .needs_saving = true;
-
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|n_coords| {
-            if let Some(chunk) = self.get_chunk_mut(n_coords) {
-                chunk.needs_saving = true;
-            }
-        })
-    }
-
     /// Propagate light on a chunk. Things this function does:
     ///
     /// 1. Spread sunlight from the very top of the chunk
@@ -1210,33 +1554,317 @@ impl Chunks {
         let chunk = self.get_chunk_mut(coords).expect("Chunk not found");
 
         chunk.needs_propagation = false;
-        chunk.needs_saving = true;
         chunk.set_lights(lights);
     }
 
-    /// Update the activities of chunks
-    fn update_activities(&mut self, coords: &Vec2<i32>) {
-        if let Some(index) = self.activities.iter().position(|c| *c == *coords) {
-            self.activities.remove(index);
+    /// Create the chunk at `coords`, loading it from disk if the world is saved.
+    ///
+    /// Load failures are handled according to `load_policy`. Returns `None` if the
+    /// chunk is refused, which keeps it out of the world until the server restarts.
+    fn load_chunk(&mut self, coords: &Vec2<i32>) -> Option<Chunk> {
+        if self.refused.contains(coords) {
+            return None;
         }
 
-        self.activities.push_back(coords.to_owned());
+        let mut chunk = Chunk::new(coords.to_owned(), &self.config, &self.chunk_folder);
+
+        if !self.config.save {
+            return Some(chunk);
+        }
+
+        // unloaded but not yet written to disk, and kept dirty in case that write fails. The
+        // chunk carries on from the generation being written, so that the report of that write
+        // can't mark the changes made since as saved
+        if let Some(data) = self.writer.pending(coords) {
+            chunk.load_data(data.to_owned());
+
+            match self.queued.get(coords) {
+                Some(&generation) => chunk.generation = generation,
+                None => chunk.mark_changed(),
+            }
+
+            return Some(chunk);
+        }
+
+        if let Err(e) = chunk.try_load() {
+            if let ChunkLoadError::Region(_) = e {
+                // a broken region can't be written to either, and setting it aside would
+                // regenerate every other chunk within it
+                error!("Chunk {:?} failed to load ({}), refusing...", coords, e);
+
+                self.refused.insert(coords.to_owned());
+                return None;
+            }
+
+            match self.load_policy {
+                ChunkLoadPolicy::Regenerate => {
+                    warn!("Chunk {:?} failed to load ({}), regenerating...", coords, e);
+                }
+                ChunkLoadPolicy::Quarantine => {
+                    if let Err(qe) = chunk.quarantine(&self.corrupt_folder, &e) {
+                        error!(
+                            "Chunk {:?} failed to load ({}) nor quarantine ({}), refusing...",
+                            coords, e, qe
+                        );
+
+                        self.refused.insert(coords.to_owned());
+                        return None;
+                    }
+
+                    warn!(
+                        "Chunk {:?} failed to load ({}), moved into {:?} and regenerating...",
+                        coords, e, self.corrupt_folder
+                    );
+                }
+                ChunkLoadPolicy::Refuse => {
+                    error!("Chunk {:?} failed to load ({}), refusing...", coords, e);
+
+                    self.refused.insert(coords.to_owned());
+                    return None;
+                }
+            }
+        }
+
+        Some(chunk)
     }
 
-    /// Unload chunks that are too old.
-    fn unload_chunks(&mut self) {
-        let diff = self.chunks.len() as i32 - self.config.max_loaded_chunks as i32;
+    /// Hand chunk snapshots of certain generations to the background writer
+    fn submit(&mut self, snapshots: Vec<(Vec2<i32>, u64, ChunkData)>) {
+        let generations = snapshots
+            .iter()
+            .map(|(coords, generation, _)| (coords.to_owned(), *generation))
+            .collect::<Vec<_>>();
 
-        if diff > 0 {
-            for _ in 0..diff {
-                if let Some(coords) = self.activities.pop_front() {
-                    if let Some(chunk) = self.chunks.remove(&coords) {
-                        if self.config.save {
-                            chunk.save();
-                        }
+        let snapshots = snapshots
+            .into_iter()
+            .map(|(coords, _, data)| (coords, data))
+            .collect();
+
+        if let Some(id) = self.writer.submit(snapshots) {
+            for (coords, generation) in generations.iter() {
+                self.queued.insert(coords.to_owned(), *generation);
+            }
+
+            self.saving.insert(id, generations);
+        }
+    }
+
+    /// Record the generations persisted by a finished background save, returning the count
+    /// of chunks persisted. Loaded chunks that failed to be written are marked for saving
+    /// again, the writer keeps the snapshots of unloaded ones until `Chunks::save` retries them.
+    fn handle_save_report(&mut self, report: SaveReport) -> usize {
+        for error in report.errors.iter() {
+            error!("Failed to save chunks: {}", error);
+        }
+
+        let generations = self.saving.remove(&report.id).unwrap_or_default();
+
+        for (coords, generation) in generations {
+            if self.queued.get(&coords) == Some(&generation) {
+                self.queued.remove(&coords);
+            }
+
+            let failed = report.failed.contains(&coords);
+
+            if let Some(chunk) = self.chunks.get_mut(&coords) {
+                if failed {
+                    if !chunk.needs_saving() {
+                        chunk.mark_changed();
+                    }
+                } else {
+                    chunk.saved_generation = chunk.saved_generation.max(generation);
+                }
+            } else if failed {
+                warn!(
+                    "Unloaded chunk {:?} failed to save, retrying on the next save...",
+                    coords
+                );
+            }
+        }
+
+        debug!(
+            "Saved {} chunks into {} regions in {:?}.",
+            report.chunks, report.regions, report.elapsed
+        );
+
+        report.chunks
+    }
+
+    /// Mark a chunk as the most recently accessed one
+    fn update_activities(&mut self, coords: &Vec2<i32>) {
+        self.access_counter += 1;
+        self.last_access
+            .insert(coords.to_owned(), self.access_counter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::gen::structures::{Structure, StructureTemplate};
+
+    use super::*;
+
+    const STONE: u32 = 1;
+    const YELLOW: u32 = 2;
+
+    /// Rolling stone terrain
+    #[derive(Debug)]
+    struct Hills;
+
+    impl TerrainGenerator for Hills {
+        fn generate(&self, chunk: &mut Chunk, _: &Registry, _: &Biomes, _: &WorldConfig) {
+            let Vec3(min_x, _, min_z) = chunk.min.to_owned();
+            let Vec3(max_x, _, max_z) = chunk.max.to_owned();
+
+            for vx in min_x..max_x {
+                for vz in min_z..max_z {
+                    for vy in 0..=3 + (vx + 2 * vz).rem_euclid(3) {
+                        chunk.set_voxel(vx, vy, vz, STONE);
                     }
                 }
             }
         }
     }
+
+    fn registry() -> Registry {
+        Registry::from_blocks(vec![
+            (0, Registry::load_block("air.json")),
+            (STONE, Registry::load_block("stone.json")),
+            (YELLOW, Registry::load_block("color-yellow.json")),
+        ])
+    }
+
+    /// Chunks of 4 voxels decorated with crosses reaching into their neighbors, saved into
+    /// `root` if any with as few chunks kept loaded as possible
+    fn chunks(root: Option<&Path>) -> Chunks {
+        // the biomes are read from the assets, relative to the root of the repository
+        std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../..")).unwrap();
+
+        let config: WorldConfig = serde_json::from_value(serde_json::json!({
+            "chunkSize": 4,
+            "dimension": 1,
+            "maxHeight": 16,
+            "maxLightLevel": 15,
+            "save": root.is_some(),
+            "chunkRoot": root.unwrap_or_else(|| Path::new("data")),
+            "renderRadius": 2,
+            "maxLoadedChunks": if root.is_some() { 0 } else { 256 },
+            "subChunks": 2,
+            "generation": "test",
+            "playerDimensions": [0.8, 1.8, 0.8],
+            "playerHead": 1.6,
+            "maxPerThread": 1,
+            "serverTickRate": 16
+        }))
+        .unwrap();
+
+        let template: StructureTemplate = serde_json::from_str(
+            r#"{
+                "name": "Cross",
+                "palette": { "S": "Stone", "*": "Color Yellow" },
+                "layers": [
+                    ["...S...", "...S...", "...S...", "SSS*SSS", "...S...", "...S...", "...S..."]
+                ],
+                "anchor": [3, 0, 3],
+                "placement": { "scale": 0.3 }
+            }"#,
+        )
+        .unwrap();
+
+        let blocks = registry();
+        let cross = Structure::new(&template, &blocks, 0).unwrap();
+
+        let mut chunks = Chunks::new("test", config, blocks, Arc::new(Hills));
+        chunks.builder = Arc::new(Builder::from_structures(registry(), 0, vec![cross]));
+        chunks
+    }
+
+    /// A pillar on the border of the chunks (0, 0) and (1, 0)
+    fn pillar(chunks: &mut Chunks) {
+        for vy in 0..16 {
+            chunks.set_voxel_by_voxel(4, vy, 0, STONE);
+        }
+    }
+
+    /// Load the chunks around some coordinates, the first pass generating their terrain and the
+    /// second decorating it
+    fn load(chunks: &mut Chunks, coords: &Vec2<i32>, radius: i16) {
+        chunks.generate(coords, radius, true);
+        chunks.generate(coords, radius, true);
+    }
+
+    #[test]
+    fn decoration_is_independent_of_load_order() {
+        let mut at_once = chunks(None);
+        load(&mut at_once, &Vec2(0, 0), 2);
+        pillar(&mut at_once);
+
+        // edited before being loaded, then decorated a few chunks at a time
+        let mut bit_by_bit = chunks(None);
+        pillar(&mut bit_by_bit);
+        for coords in [Vec2(2, 0), Vec2(-1, -1), Vec2(0, 2), Vec2(-2, 1)].iter() {
+            load(&mut bit_by_bit, coords, 1);
+        }
+        load(&mut bit_by_bit, &Vec2(0, 0), 2);
+
+        let mut compared = 0;
+
+        for chunk in at_once.all() {
+            let other = match bit_by_bit.raw(&chunk.coords) {
+                Some(other) if !chunk.needs_decoration && !other.needs_decoration => other,
+                _ => continue,
+            };
+
+            assert_eq!(
+                chunk.get_voxels().data,
+                other.get_voxels().data,
+                "chunk {:?}",
+                chunk.coords
+            );
+            assert_eq!(chunk.get_height_map().data, other.get_height_map().data);
+
+            compared += 1;
+        }
+
+        assert_eq!(compared, 13);
+        assert!(at_once
+            .all()
+            .iter()
+            .any(|chunk| chunk.get_voxels().data.contains(&YELLOW)));
+
+        // the edits queued before loading are kept over the decorations, padding included
+        assert_eq!(bit_by_bit.get_voxel_by_voxel(4, 15, 0), STONE);
+        assert_eq!(
+            bit_by_bit.raw(&Vec2(0, 0)).unwrap().get_voxel(4, 15, 0),
+            STONE
+        );
+    }
+
+    #[test]
+    fn keep_changes_made_while_unloaded_chunks_are_written() {
+        let mut root = std::env::temp_dir();
+        root.push(format!("minejs-chunks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut chunks = chunks(Some(&root));
+        load(&mut chunks, &Vec2(0, 0), 1);
+
+        // reloaded before the write of its eviction is reported, then changed
+        assert!(chunks.unload(&HashSet::new()) > 0);
+        chunks.load_saved(&Vec2(0, 0));
+        chunks.set_voxel_by_voxel(1, 10, 1, YELLOW);
+        chunks.flush();
+
+        assert!(chunks.raw(&Vec2(0, 0)).unwrap().needs_saving());
+
+        // and clean once they're written
+        assert!(chunks.save() > 0);
+        chunks.flush();
+
+        assert!(!chunks.raw(&Vec2(0, 0)).unwrap().needs_saving());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,1038 @@
+use specs::WorldExt;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use server_common::vec::Vec3;
+use server_utils::convert::{map_voxel_to_chunk, map_world_to_voxel};
+
+use crate::comp::rigidbody::RigidBody;
+use crate::constants::SCHEMATICS_FOLDER;
+use crate::gen::blocks::Blocks;
+
+use super::{
+    chunks::Chunks,
+    edits::{Cuboid, EditBatch, VoxelEdit},
+    history::History,
+    journal::{self, Journal, JournalFilter},
+    permissions::{Permissions, Role},
+    players::Players,
+    registry::Registry,
+    schematic::{Schematic, Transform},
+    world::{World, WorldConfig},
+};
+
+/// Kind of value a command argument takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgType {
+    /// A whole number
+    Integer,
+    /// Three voxel coordinates, each absolute or relative to the sender with `~`
+    Position,
+    /// A block by name, spaces written as-is or as underscores, or by id
+    Block,
+    /// A player connected to the world, by name
+    Player,
+    /// A player role, see `Role::from_name`
+    Role,
+    /// Any single word
+    Word,
+}
+
+/// A parsed command argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    Position(Vec3<i32>),
+    Block(u32),
+    Player(usize),
+    Role(Role),
+    Word(String),
+}
+
+/// An argument a command takes
+#[derive(Debug, Clone)]
+pub struct Argument {
+    pub name: &'static str,
+    pub arg_type: ArgType,
+    pub optional: bool,
+}
+
+impl Argument {
+    pub fn required(name: &'static str, arg_type: ArgType) -> Self {
+        Self {
+            name,
+            arg_type,
+            optional: false,
+        }
+    }
+
+    pub fn optional(name: &'static str, arg_type: ArgType) -> Self {
+        Self {
+            name,
+            arg_type,
+            optional: true,
+        }
+    }
+}
+
+/// Parsed arguments of a command, by the index of the command's arguments
+#[derive(Debug, Default)]
+pub struct Args(Vec<Option<Value>>);
+
+impl Args {
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.0.get(index).and_then(|v| v.as_ref())
+    }
+
+    pub fn integer(&self, index: usize) -> Option<i32> {
+        match self.get(index) {
+            Some(Value::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn position(&self, index: usize) -> Option<Vec3<i32>> {
+        match self.get(index) {
+            Some(Value::Position(value)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn block(&self, index: usize) -> Option<u32> {
+        match self.get(index) {
+            Some(Value::Block(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn player(&self, index: usize) -> Option<usize> {
+        match self.get(index) {
+            Some(Value::Player(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn role(&self, index: usize) -> Option<Role> {
+        match self.get(index) {
+            Some(Value::Role(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn word(&self, index: usize) -> Option<&str> {
+        match self.get(index) {
+            Some(Value::Word(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The player running a command
+#[derive(Debug, Clone)]
+pub struct CommandSender {
+    pub id: usize,
+    pub name: String,
+    pub role: Role,
+
+    /// Voxel the player stands in, origin of relative coordinates
+    pub position: Vec3<i32>,
+}
+
+/// Runs a command, returning the message answered to the sender or an error
+pub type CommandHandler = fn(&mut World, &CommandSender, &Args) -> Result<String, String>;
+
+/// A chat command, run by typing `/<name> <args...>`
+#[derive(Clone)]
+pub struct Command {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub args: Vec<Argument>,
+
+    /// Lowest role allowed to run the command
+    pub role: Role,
+
+    pub handler: CommandHandler,
+}
+
+impl Command {
+    /// Usage of the command, such as `/grant <player> <role>`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+
+        for arg in self.args.iter() {
+            let name = match arg.arg_type {
+                ArgType::Position => format!("{} x y z", arg.name),
+                _ => arg.name.to_owned(),
+            };
+
+            if arg.optional {
+                usage.push_str(&format!(" [{}]", name));
+            } else {
+                usage.push_str(&format!(" <{}>", name));
+            }
+        }
+
+        usage
+    }
+
+    /// Parse the words typed after the command's name into its arguments.
+    ///
+    /// Optional arguments that don't match are skipped, leaving their words to the next ones.
+    pub fn parse(
+        &self,
+        words: &[&str],
+        origin: &Vec3<i32>,
+        registry: &Registry,
+        players: &Players,
+    ) -> Result<Args, String> {
+        let mut values = vec![];
+        let mut rest = words;
+        let mut skipped = None;
+
+        for arg in self.args.iter() {
+            if rest.is_empty() {
+                if !arg.optional {
+                    return Err(format!("Missing {}", arg.name));
+                }
+
+                values.push(None);
+                continue;
+            }
+
+            match parse_argument(arg, rest, origin, registry, players) {
+                Ok((value, used)) => {
+                    values.push(Some(value));
+                    rest = &rest[used..];
+                }
+                Err(e) if arg.optional => {
+                    values.push(None);
+                    skipped = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !rest.is_empty() {
+            return Err(skipped.unwrap_or_else(|| "Too many arguments".to_owned()));
+        }
+
+        Ok(Args(values))
+    }
+}
+
+/// Parse an argument out of the first words, returning its value and the words used
+fn parse_argument(
+    arg: &Argument,
+    words: &[&str],
+    origin: &Vec3<i32>,
+    registry: &Registry,
+    players: &Players,
+) -> Result<(Value, usize), String> {
+    let value = match arg.arg_type {
+        ArgType::Integer => (
+            Value::Integer(
+                words[0]
+                    .parse()
+                    .map_err(|_| format!("\"{}\" is not a whole number", words[0]))?,
+            ),
+            1,
+        ),
+        ArgType::Position => {
+            if words.len() < 3 {
+                return Err(format!("{} needs three coordinates", arg.name));
+            }
+
+            let mut coords = [0; 3];
+
+            for (i, word) in words[..3].iter().enumerate() {
+                let axis = [origin.0, origin.1, origin.2][i];
+                coords[i] = parse_coordinate(word, axis)
+                    .ok_or_else(|| format!("\"{}\" is not a coordinate", word))?;
+            }
+
+            (Value::Position(Vec3(coords[0], coords[1], coords[2])), 3)
+        }
+        ArgType::Block => parse_block(words, registry)
+            .map(|(id, used)| (Value::Block(id), used))
+            .ok_or_else(|| format!("Unknown block \"{}\"", words[0]))?,
+        ArgType::Player => (
+            Value::Player(
+                players
+                    .iter()
+                    .find(|(_, player)| player.name.as_deref() == Some(words[0]))
+                    .map(|(&id, _)| id)
+                    .ok_or_else(|| format!("No player named \"{}\" here", words[0]))?,
+            ),
+            1,
+        ),
+        ArgType::Role => (
+            Value::Role(
+                Role::from_name(words[0])
+                    .ok_or_else(|| format!("Unknown role \"{}\"", words[0]))?,
+            ),
+            1,
+        ),
+        ArgType::Word => (Value::Word(words[0].to_owned()), 1),
+    };
+
+    Ok(value)
+}
+
+/// Parse a coordinate, either absolute or relative to `origin` such as `~` or `~-3`
+pub fn parse_coordinate(word: &str, origin: i32) -> Option<i32> {
+    match word.strip_prefix('~') {
+        Some("") => Some(origin),
+        Some(offset) => offset
+            .parse::<i32>()
+            .ok()
+            .and_then(|offset| origin.checked_add(offset)),
+        None => word.parse().ok(),
+    }
+}
+
+/// Parse the block named by the most words possible, returning its id and the words used
+fn parse_block(words: &[&str], registry: &Registry) -> Option<(u32, usize)> {
+    if let Ok(id) = words[0].parse::<u32>() {
+        return if registry.has_type(id) {
+            Some((id, 1))
+        } else {
+            None
+        };
+    }
+
+    (1..=words.len()).rev().find_map(|count| {
+        let name = words[..count].join(" ").replace('_', " ");
+
+        registry
+            .find_name(&name)
+            .map(|name| (*registry.get_id_by_name(name), count))
+    })
+}
+
+/// Resource of the chat commands of a world by name
+#[derive(Default, Clone)]
+pub struct Commands {
+    commands: BTreeMap<&'static str, Command>,
+}
+
+impl Commands {
+    /// Create the built-in commands
+    pub fn new() -> Self {
+        let mut commands = Self::default();
+
+        commands.register(Command {
+            name: "help",
+            description: "List the commands you can run, or how to use one",
+            args: vec![Argument::optional("command", ArgType::Word)],
+            role: Role::Visitor,
+            handler: help,
+        });
+
+        commands.register(Command {
+            name: "tp",
+            description: "Teleport to a position or to another player",
+            args: vec![
+                Argument::optional("position", ArgType::Position),
+                Argument::optional("player", ArgType::Player),
+            ],
+            role: Role::Builder,
+            handler: tp,
+        });
+
+        commands.register(Command {
+            name: "setblock",
+            description: "Set a single block",
+            args: vec![
+                Argument::required("position", ArgType::Position),
+                Argument::required("block", ArgType::Block),
+            ],
+            role: Role::Builder,
+            handler: setblock,
+        });
+
+        commands.register(Command {
+            name: "fill",
+            description: "Fill the area between two corners with a block",
+            args: vec![
+                Argument::required("from", ArgType::Position),
+                Argument::required("to", ArgType::Position),
+                Argument::required("block", ArgType::Block),
+            ],
+            role: Role::Builder,
+            handler: fill,
+        });
+
+        commands.register(Command {
+            name: "clone",
+            description: "Copy the area between two corners to another corner",
+            args: vec![
+                Argument::required("from", ArgType::Position),
+                Argument::required("to", ArgType::Position),
+                Argument::required("destination", ArgType::Position),
+            ],
+            role: Role::Builder,
+            handler: clone,
+        });
+
+        commands.register(Command {
+            name: "export",
+            description: "Save the area between two corners as a schematic",
+            args: vec![
+                Argument::required("name", ArgType::Word),
+                Argument::required("from", ArgType::Position),
+                Argument::required("to", ArgType::Position),
+            ],
+            role: Role::Operator,
+            handler: export,
+        });
+
+        commands.register(Command {
+            name: "import",
+            description:
+                "Paste a schematic from a corner, turned by degrees and mirrored along x, z or xz",
+            args: vec![
+                Argument::required("name", ArgType::Word),
+                Argument::required("position", ArgType::Position),
+                Argument::optional("degrees", ArgType::Integer),
+                Argument::optional("mirror", ArgType::Word),
+            ],
+            role: Role::Builder,
+            handler: import,
+        });
+
+        commands.register(Command {
+            name: "undo",
+            description: "Undo your last edit",
+            args: vec![],
+            role: Role::Builder,
+            handler: undo,
+        });
+
+        commands.register(Command {
+            name: "redo",
+            description: "Redo your last undone edit",
+            args: vec![],
+            role: Role::Builder,
+            handler: redo,
+        });
+
+        commands.register(Command {
+            name: "changes",
+            description: "List the latest block changes of a player, or of everyone with *",
+            args: vec![
+                Argument::required("player|*", ArgType::Word),
+                Argument::required("minutes", ArgType::Integer),
+                Argument::optional("radius", ArgType::Integer),
+            ],
+            role: Role::Operator,
+            handler: changes,
+        });
+
+        commands.register(Command {
+            name: "rollback",
+            description: "Revert the block changes of a player, or of everyone with *",
+            args: vec![
+                Argument::required("player|*", ArgType::Word),
+                Argument::required("minutes", ArgType::Integer),
+                Argument::optional("radius", ArgType::Integer),
+            ],
+            role: Role::Operator,
+            handler: rollback,
+        });
+
+        commands.register(Command {
+            name: "save",
+            description: "Save the world's changed chunks",
+            args: vec![],
+            role: Role::Operator,
+            handler: save,
+        });
+
+        commands.register(Command {
+            name: "backup",
+            description: "Back the world up while it keeps running",
+            args: vec![],
+            role: Role::Operator,
+            handler: backup,
+        });
+
+        commands.register(Command {
+            name: "summon",
+            description: "Summon a test entity",
+            args: vec![],
+            role: Role::Operator,
+            handler: summon,
+        });
+
+        commands.register(Command {
+            name: "grant",
+            description: "Grant a role to a player",
+            args: vec![
+                Argument::required("player", ArgType::Word),
+                Argument::required("role", ArgType::Role),
+            ],
+            role: Role::Operator,
+            handler: grant,
+        });
+
+        commands.register(Command {
+            name: "revoke",
+            description: "Revoke the role granted to a player",
+            args: vec![Argument::required("player", ArgType::Word)],
+            role: Role::Operator,
+            handler: revoke,
+        });
+
+        commands.register(Command {
+            name: "role",
+            description: "Show the role of a player, yours by default",
+            args: vec![Argument::optional("player", ArgType::Word)],
+            role: Role::Visitor,
+            handler: role,
+        });
+
+        commands
+    }
+
+    /// Register a command, replacing any previous command of the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.insert(command.name, command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Commands a role is allowed to run, by name
+    pub fn available(&self, role: Role) -> Vec<&Command> {
+        self.commands
+            .values()
+            .filter(|command| command.role <= role)
+            .collect()
+    }
+}
+
+fn help(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let commands = world.read_resource::<Commands>();
+
+    if let Some(name) = args.word(0) {
+        let name = name.trim_start_matches('/');
+
+        return match commands.get(name) {
+            Some(command) if command.role <= sender.role => {
+                Ok(format!("{}: {}", command.usage(), command.description))
+            }
+            _ => Err(format!("Unknown command \"{}\"", name)),
+        };
+    }
+
+    Ok(commands
+        .available(sender.role)
+        .iter()
+        .map(|command| format!("{} - {}", command.usage(), command.description))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn tp(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let destination = match (args.position(0), args.player(1)) {
+        (Some(position), None) => position,
+        (None, Some(id)) => {
+            let players = world.read_resource::<Players>();
+            let bodies = world.ecs().read_storage::<RigidBody>();
+            let dimension = world.read_resource::<WorldConfig>().dimension;
+
+            let Vec3(px, py, pz) = players
+                .get(&id)
+                .and_then(|player| bodies.get(player.entity))
+                .map(|body| body.get_position())
+                .ok_or_else(|| "The player has left".to_owned())?;
+
+            map_world_to_voxel(px, py, pz, dimension)
+        }
+        _ => return Err("Teleport either to a position or to a player".to_owned()),
+    };
+
+    world.teleport(sender.id, &destination)?;
+
+    Ok(format!(
+        "Teleported to {} {} {}.",
+        destination.0, destination.1, destination.2
+    ))
+}
+
+/// The cuboid between two corners cut to the world's height, if small enough for a bulk edit
+fn bulk_cuboid(world: &World, a: &Vec3<i32>, b: &Vec3<i32>) -> Result<Cuboid, String> {
+    let config = world.read_resource::<WorldConfig>();
+
+    let cuboid = Cuboid::new(a, b)
+        .clamp_height(config.max_height)
+        .ok_or_else(|| "The area is out of the world's height".to_owned())?;
+
+    if cuboid.volume() > config.edits.max_bulk_volume {
+        return Err(format!(
+            "The area has {} voxels, more than the {} allowed",
+            cuboid.volume(),
+            config.edits.max_bulk_volume
+        ));
+    }
+
+    Ok(cuboid)
+}
+
+fn setblock(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let voxel = args.position(0).unwrap();
+    let id = args.block(1).unwrap();
+
+    let batch = world.apply_edits(vec![VoxelEdit {
+        voxel: voxel.clone(),
+        id,
+        rotation: 0,
+        y_rotation: 0,
+        stage: 0,
+    }])?;
+
+    if batch.is_empty() {
+        return Err("The block is already there".to_owned());
+    }
+
+    let note = journal(world, sender, &batch);
+    world
+        .write_resource::<History>()
+        .record(&sender.name, batch);
+
+    Ok(format!(
+        "Set the block at {} {} {}.{}",
+        voxel.0, voxel.1, voxel.2, note
+    ))
+}
+
+fn fill(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let cuboid = bulk_cuboid(
+        world,
+        &args.position(0).unwrap(),
+        &args.position(1).unwrap(),
+    )?;
+    let id = args.block(2).unwrap();
+
+    let edits = cuboid
+        .voxels()
+        .map(|voxel| VoxelEdit {
+            voxel,
+            id,
+            rotation: 0,
+            y_rotation: 0,
+            stage: 0,
+        })
+        .collect();
+
+    let batch = world.apply_edits(edits)?;
+    let count = batch.len();
+
+    let note = journal(world, sender, &batch);
+    world
+        .write_resource::<History>()
+        .record(&sender.name, batch);
+
+    Ok(format!("Filled {} blocks.{}", count, note))
+}
+
+fn clone(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let source = bulk_cuboid(
+        world,
+        &args.position(0).unwrap(),
+        &args.position(1).unwrap(),
+    )?;
+    let target = source
+        .moved_to(&args.position(2).unwrap())
+        .ok_or_else(|| "The destination is too far out".to_owned())?;
+
+    // everything is read before writing, so that overlapping areas copy correctly
+    let edits = world
+        .read_voxels(&source)?
+        .into_iter()
+        .zip(target.voxels())
+        .map(|(edit, voxel)| VoxelEdit { voxel, ..edit })
+        .collect();
+
+    let batch = world.apply_edits(edits)?;
+    let count = batch.len();
+
+    let note = journal(world, sender, &batch);
+    world
+        .write_resource::<History>()
+        .record(&sender.name, batch);
+
+    Ok(format!("Cloned {} blocks.{}", count, note))
+}
+
+/// Path to a schematic by name, shared by the worlds stored in the same folder
+fn schematic_path(world: &World, name: &str) -> Result<PathBuf, String> {
+    let mut folder = PathBuf::from(&world.read_resource::<WorldConfig>().chunk_root);
+    folder.push(SCHEMATICS_FOLDER);
+
+    Schematic::path_of(&folder, name).ok_or_else(|| {
+        format!(
+            "\"{}\" isn't a valid name, use letters, digits, - and _",
+            name
+        )
+    })
+}
+
+fn export(world: &mut World, _: &CommandSender, args: &Args) -> Result<String, String> {
+    let name = args.word(0).unwrap();
+    let path = schematic_path(world, name)?;
+
+    let cuboid = bulk_cuboid(
+        world,
+        &args.position(1).unwrap(),
+        &args.position(2).unwrap(),
+    )?;
+    let voxels = world.read_voxels(&cuboid)?;
+
+    let schematic =
+        Schematic::from_voxels(&cuboid, &voxels, &world.read_resource::<Chunks>().registry);
+
+    schematic
+        .save(&path)
+        .map_err(|e| format!("Could not save the schematic: {}", e))?;
+
+    Ok(format!("Exported {} blocks as \"{}\".", voxels.len(), name))
+}
+
+fn import(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let name = args.word(0).unwrap();
+    let origin = args.position(1).unwrap();
+    let transform = Transform::parse(args.integer(2), args.word(3))?;
+
+    let schematic = Schematic::load(&schematic_path(world, name)?)
+        .map_err(|e| format!("Could not load the schematic \"{}\": {}", name, e))?;
+
+    let size = transform.size(&schematic.size);
+    let target = Cuboid::new(&Vec3(0, 0, 0), &size.sub(&Vec3(1, 1, 1)))
+        .moved_to(&origin)
+        .ok_or_else(|| "The destination is too far out".to_owned())?;
+    bulk_cuboid(world, &target.min, &target.max)?;
+
+    let edits = schematic.to_edits(
+        &origin,
+        &transform,
+        &world.read_resource::<Chunks>().registry,
+    )?;
+
+    let batch = world.apply_edits(edits)?;
+    let count = batch.len();
+
+    let note = journal(world, sender, &batch);
+    world
+        .write_resource::<History>()
+        .record(&sender.name, batch);
+
+    Ok(format!(
+        "Imported \"{}\", changing {} blocks.{}",
+        name, count, note
+    ))
+}
+
+fn undo(world: &mut World, sender: &CommandSender, _: &Args) -> Result<String, String> {
+    let batch = world
+        .write_resource::<History>()
+        .take_undo(&sender.name)
+        .ok_or_else(|| "Nothing to undo".to_owned())?;
+
+    let note = match world.apply_edits(batch.undo_edits()) {
+        Ok(undone) => journal(world, sender, &undone),
+        Err(e) => {
+            world
+                .write_resource::<History>()
+                .push_undo(&sender.name, batch);
+            return Err(e);
+        }
+    };
+
+    let count = batch.len();
+    world
+        .write_resource::<History>()
+        .push_redo(&sender.name, batch);
+
+    Ok(format!("Undid the changes of {} blocks.{}", count, note))
+}
+
+fn redo(world: &mut World, sender: &CommandSender, _: &Args) -> Result<String, String> {
+    let batch = world
+        .write_resource::<History>()
+        .take_redo(&sender.name)
+        .ok_or_else(|| "Nothing to redo".to_owned())?;
+
+    let note = match world.apply_edits(batch.redo_edits()) {
+        Ok(redone) => journal(world, sender, &redone),
+        Err(e) => {
+            world
+                .write_resource::<History>()
+                .push_redo(&sender.name, batch);
+            return Err(e);
+        }
+    };
+
+    let count = batch.len();
+    world
+        .write_resource::<History>()
+        .push_undo(&sender.name, batch);
+
+    Ok(format!("Redid the changes of {} blocks.{}", count, note))
+}
+
+/// Journal the edits of a command, returning a note for its reply if they couldn't be
+fn journal(world: &mut World, sender: &CommandSender, batch: &EditBatch) -> String {
+    match world.journal_edits(sender.id, &sender.name, batch) {
+        Ok(()) => String::new(),
+        Err(e) => format!(
+            " The changes couldn't be journaled ({}), so they can't be rolled back.",
+            e
+        ),
+    }
+}
+
+/// Amount of changes listed by `/changes`
+const LISTED_CHANGES: usize = 5;
+
+/// The journal entries of a player within the last minutes, and within a radius of the sender
+fn journal_filter(sender: &CommandSender, args: &Args) -> Result<JournalFilter, String> {
+    let player = match args.word(0).unwrap() {
+        "*" => None,
+        name => Some(name.to_owned()),
+    };
+
+    let minutes = args.integer(1).unwrap();
+    if minutes <= 0 {
+        return Err("The amount of minutes has to be positive".to_owned());
+    }
+
+    let area = match args.integer(2) {
+        Some(radius) if radius < 0 => return Err("The radius can't be negative".to_owned()),
+        Some(radius) => Some((sender.position.clone(), radius)),
+        None => None,
+    };
+
+    Ok(JournalFilter {
+        player,
+        since: journal::now().saturating_sub(minutes as u64 * 60),
+        area,
+    })
+}
+
+fn changes(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let entries = world
+        .read_resource::<Journal>()
+        .query(&journal_filter(sender, args)?)
+        .map_err(|e| format!("Could not read the journal: {}", e))?;
+
+    if entries.is_empty() {
+        return Ok("No changes found.".to_owned());
+    }
+
+    let now = journal::now();
+    let chunks = world.read_resource::<Chunks>();
+    let name_of = |raw: u32| {
+        &chunks
+            .registry
+            .get_block_by_id(Blocks::extract_id(raw))
+            .name
+    };
+
+    let latest = entries
+        .iter()
+        .rev()
+        .take(LISTED_CHANGES)
+        .map(|entry| {
+            format!(
+                "{} {} -> {} at {} {} {} {}m ago",
+                entry.player,
+                name_of(entry.old),
+                name_of(entry.new),
+                entry.voxel.0,
+                entry.voxel.1,
+                entry.voxel.2,
+                now.saturating_sub(entry.time) / 60
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(format!(
+        "{} changes found, latest: {}.",
+        entries.len(),
+        latest.join(", ")
+    ))
+}
+
+fn rollback(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let entries = world
+        .read_resource::<Journal>()
+        .query(&journal_filter(sender, args)?)
+        .map_err(|e| format!("Could not read the journal: {}", e))?;
+
+    let edits = journal::rollback_edits(&entries);
+
+    let max_volume = world.read_resource::<WorldConfig>().edits.max_bulk_volume;
+    if edits.len() > max_volume {
+        return Err(format!(
+            "The rollback restores {} blocks, more than the {} allowed",
+            edits.len(),
+            max_volume
+        ));
+    }
+
+    // chunks unloaded since are loaded back, the ones that can't be are left as they are
+    let mut chunks = world.write_resource::<Chunks>();
+    let chunk_size = chunks.config.chunk_size;
+    let mut ready = HashMap::new();
+
+    let (edits, skipped): (Vec<_>, Vec<_>) = edits.into_iter().partition(|edit| {
+        let Vec3(vx, vy, vz) = edit.voxel;
+        let coords = map_voxel_to_chunk(vx, vy, vz, chunk_size);
+
+        *ready
+            .entry(coords)
+            .or_insert_with_key(|coords| chunks.load_saved(coords))
+    });
+
+    drop(chunks);
+
+    let batch = world.apply_edits(edits)?;
+    let count = batch.len();
+
+    let note = journal(world, sender, &batch);
+    world
+        .write_resource::<History>()
+        .record(&sender.name, batch);
+
+    let mut message = format!(
+        "Rolled back {} changes, restoring {} blocks.",
+        entries.len(),
+        count
+    );
+
+    if !skipped.is_empty() {
+        message.push_str(&format!(
+            " {} blocks in chunks that couldn't be loaded were left as they are.",
+            skipped.len()
+        ));
+    }
+
+    message.push_str(&note);
+
+    Ok(message)
+}
+
+fn save(world: &mut World, _: &CommandSender, _: &Args) -> Result<String, String> {
+    let count = world.save();
+    Ok(format!("Saving {} changed chunks...", count))
+}
+
+fn backup(world: &mut World, _: &CommandSender, _: &Args) -> Result<String, String> {
+    match world.backup() {
+        Ok(path) => Ok(format!(
+            "Creating backup {:?}...",
+            path.file_name().unwrap()
+        )),
+        Err(e) => Err(format!("Backup failed: {}", e)),
+    }
+}
+
+fn summon(world: &mut World, sender: &CommandSender, _: &Args) -> Result<String, String> {
+    world.test_entity(sender.id);
+    Ok("Summoned a test entity.".to_owned())
+}
+
+fn grant(world: &mut World, _: &CommandSender, args: &Args) -> Result<String, String> {
+    let name = args.word(0).unwrap();
+    let role = args.role(1).unwrap();
+
+    world
+        .write_resource::<Permissions>()
+        .grant(name, role)
+        .map_err(|e| format!("Could not save permissions: {}", e))?;
+
+    Ok(format!("{} is now a {}.", name, role.name()))
+}
+
+fn revoke(world: &mut World, _: &CommandSender, args: &Args) -> Result<String, String> {
+    let name = args.word(0).unwrap();
+    let mut permissions = world.write_resource::<Permissions>();
+
+    if permissions.is_configured_operator(name) {
+        return Err(format!("{} is an operator by the world's config.", name));
+    }
+
+    match permissions.revoke(name) {
+        Ok(Some(old_role)) => Ok(format!(
+            "{} is no longer a {}, now a {}.",
+            name,
+            old_role.name(),
+            permissions.role_of(Some(name)).name()
+        )),
+        Ok(None) => Err(format!("{} wasn't granted any role.", name)),
+        Err(e) => Err(format!("Could not save permissions: {}", e)),
+    }
+}
+
+fn role(world: &mut World, sender: &CommandSender, args: &Args) -> Result<String, String> {
+    let name = args.word(0).unwrap_or(&sender.name);
+    let role = world.read_resource::<Permissions>().role_of(Some(name));
+
+    Ok(format!("{} is a {}.", name, role.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_and_usage() {
+        assert_eq!(parse_coordinate("12", 5), Some(12));
+        assert_eq!(parse_coordinate("~", 5), Some(5));
+        assert_eq!(parse_coordinate("~-3", 5), Some(2));
+        assert_eq!(parse_coordinate("~x", 5), None);
+        assert_eq!(parse_coordinate("~1", i32::MAX), None);
+
+        let command = Command {
+            name: "fill",
+            description: "",
+            args: vec![
+                Argument::required("from", ArgType::Position),
+                Argument::required("block", ArgType::Block),
+                Argument::optional("player", ArgType::Player),
+            ],
+            role: Role::Builder,
+            handler: |_, _, _| Ok(String::new()),
+        };
+
+        assert_eq!(command.usage(), "/fill <from x y z> <block> [player]");
+    }
+
+    #[test]
+    fn skip_optional_arguments() {
+        let registry = Registry::from_blocks(vec![]);
+        let players = Players::new();
+        let origin = Vec3(0, i32::MAX, 0);
+
+        let tp = Commands::new().get("tp").unwrap().to_owned();
+        let parse = |words: &[&str]| tp.parse(words, &origin, &registry, &players);
+
+        assert_eq!(tp.usage(), "/tp [position x y z] [player]");
+
+        let args = parse(&["1", "~", "~-2"]).unwrap();
+        assert_eq!(args.position(0), Some(Vec3(1, i32::MAX, -2)));
+        assert_eq!(args.player(1), None);
+
+        assert!(parse(&[]).unwrap().get(0).is_none());
+        assert_eq!(
+            parse(&["steve"]).unwrap_err(),
+            "No player named \"steve\" here"
+        );
+        assert!(parse(&["1", "~1", "3"]).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use serde::Deserialize;
+
+use std::time::{Duration, Instant};
+
+use server_common::vec::{Vec2, Vec3};
+use server_utils::convert::map_voxel_to_chunk;
+
+use crate::gen::blocks::{BlockRotation, Blocks};
+
+/// How block edits from players are validated, configured per world through `edits` in `worlds.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditConfig {
+    /// Farthest distance in voxels from a player's head to the center of a voxel they edit
+    #[serde(default = "default_reach")]
+    pub reach: f32,
+
+    /// Whether nothing solid can stand between a player's head and the voxels they edit
+    #[serde(default = "default_line_of_sight")]
+    pub line_of_sight: bool,
+
+    /// Maximum amount of voxels a player can edit per second
+    #[serde(default = "default_max_edits_per_second")]
+    pub max_edits_per_second: u32,
+
+    /// Maximum amount of voxels changed by a single bulk edit, such as `/fill`
+    #[serde(default = "default_max_bulk_volume")]
+    pub max_bulk_volume: usize,
+
+    /// Amount of edits each player can undo
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+}
+
+fn default_reach() -> f32 {
+    8.0
+}
+
+fn default_line_of_sight() -> bool {
+    true
+}
+
+fn default_max_edits_per_second() -> u32 {
+    20
+}
+
+fn default_max_bulk_volume() -> usize {
+    32768
+}
+
+fn default_history_size() -> usize {
+    20
+}
+
+impl Default for EditConfig {
+    fn default() -> Self {
+        Self {
+            reach: default_reach(),
+            line_of_sight: default_line_of_sight(),
+            max_edits_per_second: default_max_edits_per_second(),
+            max_bulk_volume: default_max_bulk_volume(),
+            history_size: default_history_size(),
+        }
+    }
+}
+
+/// Why a block edit from a player got rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditRejection {
+    /// Above or below the world, of an unknown type, or in a chunk that isn't ready
+    Invalid,
+    /// The player's role doesn't allow editing blocks
+    Forbidden,
+    /// Too many edits within the last second
+    RateLimited,
+    /// Too far from the player's head
+    OutOfReach,
+    /// Hidden behind solid blocks
+    OutOfSight,
+    /// A solid block placed inside of an entity
+    InsideEntity,
+}
+
+/// Edits made by a player within the current second, to rate limit them
+#[derive(Debug)]
+pub struct EditLimiter {
+    window: Instant,
+    count: u32,
+}
+
+impl Default for EditLimiter {
+    fn default() -> Self {
+        Self {
+            window: Instant::now(),
+            count: 0,
+        }
+    }
+}
+
+impl EditLimiter {
+    /// Count an edit made at `now`, returning whether it is within `max_per_second`
+    pub fn allow(&mut self, now: Instant, max_per_second: u32) -> bool {
+        if now.duration_since(self.window) >= Duration::from_secs(1) {
+            self.window = now;
+            self.count = 0;
+        }
+
+        if self.count >= max_per_second {
+            return false;
+        }
+
+        self.count += 1;
+        true
+    }
+}
+
+/// A change of a voxel made by an edit, applied through `World::apply_edits`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelEdit {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+    pub rotation: u32,
+    pub y_rotation: u32,
+    pub stage: u32,
+}
+
+impl VoxelEdit {
+    /// Read an edit from a raw voxel value, with its id, rotation and stage
+    pub fn from_raw(voxel: Vec3<i32>, raw: u32) -> Self {
+        let (rotation, y_rotation) = BlockRotation::decode(&Blocks::extract_rotation(raw));
+
+        Self {
+            voxel,
+            id: Blocks::extract_id(raw),
+            rotation,
+            y_rotation,
+            stage: Blocks::extract_stage(raw),
+        }
+    }
+
+    /// The raw voxel value the edit sets
+    pub fn to_raw(&self) -> u32 {
+        let raw = Blocks::insert_id(0, self.id);
+        let raw =
+            Blocks::insert_rotation(raw, &BlockRotation::encode(self.rotation, self.y_rotation));
+        Blocks::insert_stage(raw, self.stage)
+    }
+}
+
+/// Edits applied together, with the state of the voxels before and after them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditBatch {
+    pub before: Vec<VoxelEdit>,
+    pub after: Vec<VoxelEdit>,
+}
+
+impl EditBatch {
+    /// Amount of voxels changed
+    pub fn len(&self) -> usize {
+        self.after.len()
+    }
+
+    /// Whether no voxel changed
+    pub fn is_empty(&self) -> bool {
+        self.after.is_empty()
+    }
+
+    /// Add the change of a voxel from `before` to `after`
+    pub fn push(&mut self, before: VoxelEdit, after: VoxelEdit) {
+        self.before.push(before);
+        self.after.push(after);
+    }
+
+    /// Edits restoring the voxels to their state before the batch, in the reverse order
+    pub fn undo_edits(&self) -> Vec<VoxelEdit> {
+        self.before.iter().rev().cloned().collect()
+    }
+
+    /// Edits making the batch's changes again
+    pub fn redo_edits(&self) -> Vec<VoxelEdit> {
+        self.after.clone()
+    }
+}
+
+/// A cuboid of voxels between two corners, both included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cuboid {
+    pub min: Vec3<i32>,
+    pub max: Vec3<i32>,
+}
+
+impl Cuboid {
+    /// Create the cuboid between any two opposite corners
+    pub fn new(a: &Vec3<i32>, b: &Vec3<i32>) -> Self {
+        Self {
+            min: Vec3(a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            max: Vec3(a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        }
+    }
+
+    /// Size of the cuboid on each axis
+    pub fn size(&self) -> Vec3<i32> {
+        Vec3(
+            self.max.0 - self.min.0 + 1,
+            self.max.1 - self.min.1 + 1,
+            self.max.2 - self.min.2 + 1,
+        )
+    }
+
+    /// Amount of voxels in the cuboid
+    pub fn volume(&self) -> usize {
+        let Vec3(x, y, z) = self.size();
+        (x as usize)
+            .saturating_mul(y as usize)
+            .saturating_mul(z as usize)
+    }
+
+    /// Cut the cuboid to the height of the world, `None` if it's entirely out of it
+    pub fn clamp_height(&self, max_height: u32) -> Option<Self> {
+        let top = max_height as i32 - 1;
+
+        if self.max.1 < 0 || self.min.1 > top {
+            return None;
+        }
+
+        Some(Self {
+            min: Vec3(self.min.0, self.min.1.max(0), self.min.2),
+            max: Vec3(self.max.0, self.max.1.min(top), self.max.2),
+        })
+    }
+
+    /// Move the cuboid so that its minimum corner is at `to`, `None` if its maximum corner
+    /// would be past the range of coordinates
+    pub fn moved_to(&self, to: &Vec3<i32>) -> Option<Self> {
+        let Vec3(x, y, z) = to.clone();
+        let Vec3(dx, dy, dz) = self.max.sub(&self.min);
+
+        Some(Self {
+            min: to.clone(),
+            max: Vec3(x.checked_add(dx)?, y.checked_add(dy)?, z.checked_add(dz)?),
+        })
+    }
+
+    /// Every voxel of the cuboid, along y then z then x
+    pub fn voxels(&self) -> impl Iterator<Item = Vec3<i32>> {
+        let Cuboid {
+            min: Vec3(min_x, min_y, min_z),
+            max: Vec3(max_x, max_y, max_z),
+        } = self.clone();
+
+        (min_x..=max_x).flat_map(move |vx| {
+            (min_z..=max_z).flat_map(move |vz| (min_y..=max_y).map(move |vy| Vec3(vx, vy, vz)))
+        })
+    }
+
+    /// Coordinates of the chunks the cuboid covers
+    pub fn chunks(&self, chunk_size: usize) -> Vec<Vec2<i32>> {
+        let Vec2(min_cx, min_cz) = map_voxel_to_chunk(self.min.0, 0, self.min.2, chunk_size);
+        let Vec2(max_cx, max_cz) = map_voxel_to_chunk(self.max.0, 0, self.max.2, chunk_size);
+
+        (min_cx..=max_cx)
+            .flat_map(|cx| (min_cz..=max_cz).map(move |cz| Vec2(cx, cz)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_edits_per_second() {
+        let start = Instant::now();
+        let mut limiter = EditLimiter::default();
+
+        assert!(limiter.allow(start, 2));
+        assert!(limiter.allow(start, 2));
+        assert!(!limiter.allow(start + Duration::from_millis(500), 2));
+
+        assert!(limiter.allow(start + Duration::from_millis(1500), 2));
+    }
+
+    #[test]
+    fn cuboid_voxels_and_chunks() {
+        let cuboid = Cuboid::new(&Vec3(3, 130, -1), &Vec3(-2, 126, 1));
+
+        assert_eq!(cuboid.min, Vec3(-2, 126, -1));
+        assert_eq!(cuboid.volume(), 6 * 5 * 3);
+
+        let clamped = cuboid.clamp_height(128).unwrap();
+        assert_eq!(clamped.max, Vec3(3, 127, 1));
+        assert_eq!(clamped.voxels().count(), clamped.volume());
+        assert!(Cuboid::new(&Vec3(0, 128, 0), &Vec3(0, 200, 0))
+            .clamp_height(128)
+            .is_none());
+
+        assert_eq!(
+            clamped.chunks(2),
+            vec![
+                Vec2(-1, -1),
+                Vec2(-1, 0),
+                Vec2(0, -1),
+                Vec2(0, 0),
+                Vec2(1, -1),
+                Vec2(1, 0)
+            ]
+        );
+
+        assert_eq!(
+            clamped.moved_to(&Vec3(10, 0, 10)).unwrap().max,
+            Vec3(15, 1, 12)
+        );
+        assert!(clamped.moved_to(&Vec3(i32::MAX - 5, 0, 0)).is_some());
+        assert!(clamped.moved_to(&Vec3(i32::MAX - 4, 0, 0)).is_none());
+    }
+}
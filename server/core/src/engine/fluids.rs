@@ -0,0 +1,250 @@
+use hashbrown::HashSet;
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+
+use server_common::vec::Vec3;
+
+use super::chunks::Chunks;
+
+/// Horizontal neighbors a fluid can flow into
+const HORIZONTAL_NEIGHBORS: [[i32; 2]; 4] = [[1, 0], [-1, 0], [0, 1], [0, -1]];
+
+/// Highest level a fluid can have, bound by the 4 bits of the voxel stage
+const MAX_FLUID_LEVEL: u32 = 15;
+
+/// How fluids flow, configured per world through `fluids` in `worlds.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FluidConfig {
+    /// Ticks for a fluid change to reach its neighbors
+    #[serde(default = "default_interval")]
+    pub interval: i32,
+
+    /// Maximum amount of voxels updated per tick, the rest wait for the next ticks
+    #[serde(default = "default_max_updates")]
+    pub max_updates: usize,
+}
+
+fn default_interval() -> i32 {
+    40
+}
+
+fn default_max_updates() -> usize {
+    256
+}
+
+impl Default for FluidConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            max_updates: default_max_updates(),
+        }
+    }
+}
+
+/// A change to a fluid voxel, `stage` being the fluid's level
+#[derive(Debug, Clone, PartialEq)]
+pub struct FluidUpdate {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+    pub stage: u32,
+}
+
+/// Resource of fluid voxels scheduled to be updated.
+///
+/// Fluid levels are stored in the voxel stage bits: sources are at level 0, and every voxel
+/// flowed away from a source is a level higher, up to the block's `fluid_range`. Falling fluids
+/// are at level 1, spreading again once they land.
+#[derive(Debug, Default)]
+pub struct Fluids {
+    pub config: FluidConfig,
+
+    scheduled: BTreeMap<i32, Vec<Vec3<i32>>>,
+    queued: HashSet<Vec3<i32>>,
+}
+
+impl Fluids {
+    pub fn new(config: FluidConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Amount of voxels waiting to be updated
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Whether no voxel is waiting to be updated
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Schedule a voxel to be updated `interval` ticks after `tick`
+    pub fn schedule(&mut self, voxel: Vec3<i32>, tick: i32) {
+        if self.queued.insert(voxel.clone()) {
+            self.scheduled
+                .entry(tick + self.config.interval)
+                .or_default()
+                .push(voxel);
+        }
+    }
+
+    /// Schedule a voxel and its six neighbors, for whenever the voxel changed
+    pub fn schedule_around(&mut self, voxel: &Vec3<i32>, tick: i32) {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        self.schedule(Vec3(vx, vy, vz), tick);
+        self.schedule(Vec3(vx, vy + 1, vz), tick);
+        self.schedule(Vec3(vx, vy - 1, vz), tick);
+
+        for [ox, oz] in HORIZONTAL_NEIGHBORS.iter() {
+            self.schedule(Vec3(vx + ox, vy, vz + oz), tick);
+        }
+    }
+
+    /// Take the voxels due by `tick`, at most `max_updates` of them
+    pub fn due(&mut self, tick: i32) -> Vec<Vec3<i32>> {
+        let mut due = vec![];
+
+        while due.len() < self.config.max_updates {
+            let mut entry = match self.scheduled.first_entry() {
+                Some(entry) if *entry.key() <= tick => entry,
+                _ => break,
+            };
+
+            let voxels = entry.get_mut();
+            let count = voxels.len().min(self.config.max_updates - due.len());
+
+            due.extend(voxels.drain(..count));
+
+            if voxels.is_empty() {
+                entry.remove();
+            }
+        }
+
+        due.iter().for_each(|voxel| {
+            self.queued.remove(voxel);
+        });
+
+        due
+    }
+
+    /// Compute what a voxel should become according to the fluids around it, if anything.
+    ///
+    /// Sources never change. Flowing fluids take the lowest level they can get from the fluid
+    /// above or the fluids beside them, drying out when nothing feeds them anymore.
+    pub fn flow(chunks: &Chunks, voxel: &Vec3<i32>) -> Option<FluidUpdate> {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        if vy < 0 || vy >= chunks.config.max_height as i32 {
+            return None;
+        }
+
+        // never flow into chunks that aren't loaded
+        chunks.get_chunk_by_voxel(vx, vy, vz)?;
+
+        let registry = &chunks.registry;
+
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        let is_fluid = registry.is_fluid(id);
+
+        if is_fluid && chunks.get_voxel_stage_by_voxel(vx, vy, vz) == 0 {
+            return None;
+        }
+
+        if !is_fluid && !registry.is_air(id) {
+            return None;
+        }
+
+        let mut incoming: Option<(u32, u32)> = None;
+
+        let above = chunks.get_voxel_by_voxel(vx, vy + 1, vz);
+
+        if vy + 1 < chunks.config.max_height as i32 && registry.is_fluid(above) {
+            incoming = Some((above, 1));
+        } else {
+            for [ox, oz] in HORIZONTAL_NEIGHBORS.iter() {
+                let (nx, nz) = (vx + ox, vz + oz);
+
+                let n_id = chunks.get_voxel_by_voxel(nx, vy, nz);
+
+                if !registry.is_fluid(n_id) {
+                    continue;
+                }
+
+                // fluids only spread sideways once they can't fall any further
+                let below = chunks.get_voxel_by_voxel(nx, vy - 1, nz);
+                if vy > 0 && (registry.is_air(below) || registry.is_fluid(below)) {
+                    continue;
+                }
+
+                let range = registry
+                    .get_block_by_id(n_id)
+                    .fluid_range
+                    .min(MAX_FLUID_LEVEL);
+                let level = chunks.get_voxel_stage_by_voxel(nx, vy, nz) + 1;
+
+                if level > range {
+                    continue;
+                }
+
+                if incoming.is_none_or(|(_, lowest)| level < lowest) {
+                    incoming = Some((n_id, level));
+                }
+            }
+        }
+
+        match incoming {
+            Some((n_id, level)) => {
+                if is_fluid && n_id == id && chunks.get_voxel_stage_by_voxel(vx, vy, vz) == level {
+                    None
+                } else {
+                    Some(FluidUpdate {
+                        voxel: voxel.clone(),
+                        id: n_id,
+                        stage: level,
+                    })
+                }
+            }
+            None if is_fluid => Some(FluidUpdate {
+                voxel: voxel.clone(),
+                id: *registry.get_id_by_name("Air"),
+                stage: 0,
+            }),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_and_take_due() {
+        let mut fluids = Fluids::new(FluidConfig {
+            interval: 5,
+            max_updates: 4,
+        });
+
+        fluids.schedule_around(&Vec3(0, 10, 0), 0);
+        fluids.schedule(Vec3(0, 10, 0), 2);
+        fluids.schedule(Vec3(100, 10, 0), 2);
+
+        assert_eq!(fluids.len(), 8);
+        assert!(fluids.due(4).is_empty());
+
+        let first = fluids.due(5);
+        assert_eq!(first.len(), 4);
+        assert_eq!(first[0], Vec3(0, 10, 0));
+
+        let second = fluids.due(6);
+        assert_eq!(second.len(), 3);
+
+        assert_eq!(fluids.due(7), vec![Vec3(100, 10, 0)]);
+        assert!(fluids.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::edits::EditBatch;
+
+/// Edits of a single player that can be undone and redone
+#[derive(Debug, Default)]
+struct PlayerHistory {
+    undo: VecDeque<EditBatch>,
+    redo: Vec<EditBatch>,
+}
+
+/// Resource of the edit histories of players by name, keeping the last `size` batches of each
+#[derive(Debug, Default)]
+pub struct History {
+    size: usize,
+    players: HashMap<String, PlayerHistory>,
+}
+
+impl History {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    /// Record a new batch of edits made by a player, which can't redo anything anymore
+    pub fn record(&mut self, player: &str, batch: EditBatch) {
+        if batch.is_empty() || self.size == 0 {
+            return;
+        }
+
+        self.players
+            .entry(player.to_owned())
+            .or_default()
+            .redo
+            .clear();
+        self.push_undo(player, batch);
+    }
+
+    /// Take the last batch a player can undo
+    pub fn take_undo(&mut self, player: &str) -> Option<EditBatch> {
+        self.players.get_mut(player)?.undo.pop_back()
+    }
+
+    /// Take the last batch a player can redo
+    pub fn take_redo(&mut self, player: &str) -> Option<EditBatch> {
+        self.players.get_mut(player)?.redo.pop()
+    }
+
+    /// Put back a batch that can be undone, keeping the batches that can be redone
+    pub fn push_undo(&mut self, player: &str, batch: EditBatch) {
+        if self.size == 0 {
+            return;
+        }
+
+        let history = self.players.entry(player.to_owned()).or_default();
+
+        history.undo.push_back(batch);
+
+        while history.undo.len() > self.size {
+            history.undo.pop_front();
+        }
+    }
+
+    /// Put back a batch that can be redone
+    pub fn push_redo(&mut self, player: &str, batch: EditBatch) {
+        if self.size == 0 {
+            return;
+        }
+
+        let history = self.players.entry(player.to_owned()).or_default();
+
+        history.redo.push(batch);
+
+        if history.redo.len() > self.size {
+            history.redo.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use server_common::vec::Vec3;
+
+    use super::*;
+
+    use crate::engine::edits::VoxelEdit;
+
+    fn batch(id: u32) -> EditBatch {
+        let edit = |id| VoxelEdit {
+            voxel: Vec3(0, 0, 0),
+            id,
+            rotation: 0,
+            y_rotation: 0,
+            stage: 0,
+        };
+
+        EditBatch {
+            before: vec![edit(0)],
+            after: vec![edit(id)],
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_within_size() {
+        let mut history = History::new(2);
+
+        history.record("steve", batch(1));
+        history.record("steve", batch(2));
+        history.record("steve", batch(3));
+
+        let undone = history.take_undo("steve").unwrap();
+        assert_eq!(undone, batch(3));
+        history.push_redo("steve", undone);
+
+        assert_eq!(history.take_undo("steve"), Some(batch(2)));
+        assert_eq!(history.take_undo("steve"), None);
+        assert_eq!(history.take_undo("alex"), None);
+
+        assert_eq!(history.take_redo("steve"), Some(batch(3)));
+
+        history.push_redo("steve", batch(4));
+        history.record("steve", batch(5));
+        assert_eq!(history.take_redo("steve"), None);
+    }
+}
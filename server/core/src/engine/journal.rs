@@ -0,0 +1,324 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use server_common::vec::Vec3;
+
+use super::edits::{EditBatch, VoxelEdit};
+
+/// A change of a single voxel by a player, as recorded in the journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub player_id: usize,
+    pub player: String,
+    pub voxel: Vec3<i32>,
+    /// Raw voxel value before the change, with its rotation and stage
+    pub old: u32,
+    /// Raw voxel value after the change
+    pub new: u32,
+}
+
+/// Which journal entries to look up
+#[derive(Debug, Clone, Default)]
+pub struct JournalFilter {
+    /// Name of the player who made the changes, any player if `None`
+    pub player: Option<String>,
+    /// Earliest time of the changes, in seconds since the unix epoch
+    pub since: u64,
+    /// Center and radius in voxels of the sphere the changes are within, anywhere if `None`
+    pub area: Option<(Vec3<i32>, i32)>,
+}
+
+impl JournalFilter {
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        if entry.time < self.since {
+            return false;
+        }
+
+        if self.player.as_ref().is_some_and(|p| *p != entry.player) {
+            return false;
+        }
+
+        self.area.as_ref().is_none_or(|(center, radius)| {
+            let Vec3(dx, dy, dz) = entry.voxel.sub(center);
+            let (dx, dy, dz) = (dx as i64, dy as i64, dz as i64);
+            let radius = *radius as i64;
+
+            dx * dx + dy * dy + dz * dz <= radius * radius
+        })
+    }
+}
+
+/// Resource of the append-only journal of every block change players make, stored as one JSON
+/// entry per line in the world's data folder
+#[derive(Debug, Default)]
+pub struct Journal {
+    path: Option<PathBuf>,
+
+    /// Entries of worlds that aren't saved, only lasting as long as the world runs
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Journal to the file at `path`, or in memory without a path
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Append the changes of a batch of edits made by a player
+    pub fn record(&mut self, player_id: usize, player: &str, batch: &EditBatch) -> io::Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let time = now();
+
+        let entries = batch
+            .before
+            .iter()
+            .zip(batch.after.iter())
+            .map(|(before, after)| JournalEntry {
+                time,
+                player_id,
+                player: player.to_owned(),
+                voxel: after.voxel.clone(),
+                old: before.to_raw(),
+                new: after.to_raw(),
+            });
+
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                self.entries.extend(entries);
+                return Ok(());
+            }
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+
+        for entry in entries {
+            serde_json::to_writer(&mut writer, &entry)?;
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()
+    }
+
+    /// Entries matching a filter, from the oldest to the newest.
+    ///
+    /// Entries are appended in time order, so only the end of the journal from `since` on is read.
+    pub fn query(&self, filter: &JournalFilter) -> io::Result<Vec<JournalEntry>> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                return Ok(self
+                    .entries
+                    .iter()
+                    .filter(|entry| filter.matches(entry))
+                    .cloned()
+                    .collect())
+            }
+        };
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let start = first_line_since(&file, filter.since)?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(start))?;
+
+        let mut entries = vec![];
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.is_empty() {
+                continue;
+            }
+
+            // a line cut short by a crash shouldn't hide the rest of the journal
+            match serde_json::from_str::<JournalEntry>(&line) {
+                Ok(entry) if filter.matches(&entry) => entries.push(entry),
+                Ok(_) => {}
+                Err(e) => warn!("Skipping broken journal entry in {:?}: {}", path, e),
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Time of a journal entry, without parsing the rest of it
+#[derive(Deserialize)]
+struct EntryTime {
+    time: u64,
+}
+
+/// Offset of the first line of a journal with an entry from `since` on, or the journal's length
+/// if there's none. Binary searches the lines, lines that can't be read counting as recent.
+fn first_line_since(file: &File, since: u64) -> io::Result<u64> {
+    // lines starting before `low` are older than `since`, lines starting from `high` aren't
+    let mut low = 0;
+    let mut high = file.metadata()?.len();
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        match line_from(file, mid)? {
+            Some((start, end, line)) if start < high => {
+                let old = serde_json::from_slice::<EntryTime>(&line)
+                    .map(|entry| entry.time < since)
+                    .unwrap_or(false);
+
+                if old {
+                    low = end;
+                } else {
+                    high = start;
+                }
+            }
+            _ => high = mid,
+        }
+    }
+
+    Ok(low)
+}
+
+/// The first line starting at or after `pos`, with the offsets it starts and ends at
+fn line_from(file: &File, pos: u64) -> io::Result<Option<(u64, u64, Vec<u8>)>> {
+    let mut reader = BufReader::new(file);
+    let mut start = pos;
+
+    if pos > 0 {
+        reader.seek(SeekFrom::Start(pos - 1))?;
+        start = pos - 1 + reader.read_until(b'\n', &mut vec![])? as u64;
+    } else {
+        reader.seek(SeekFrom::Start(0))?;
+    }
+
+    let mut line = vec![];
+    let read = reader.read_until(b'\n', &mut line)?;
+
+    if read == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some((start, start + read as u64, line)))
+}
+
+/// Edits restoring every voxel of the entries to its state before the earliest of them.
+///
+/// Changes made to the same voxels after the entries are overwritten.
+pub fn rollback_edits(entries: &[JournalEntry]) -> Vec<VoxelEdit> {
+    let mut seen = HashSet::new();
+
+    entries
+        .iter()
+        .filter(|entry| seen.insert(entry.voxel.clone()))
+        .map(|entry| VoxelEdit::from_raw(entry.voxel.clone(), entry.old))
+        .collect()
+}
+
+/// Seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards.")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(time: u64, player: &str, voxel: Vec3<i32>, old: u32, new: u32) -> JournalEntry {
+        JournalEntry {
+            time,
+            player_id: 0,
+            player: player.to_owned(),
+            voxel,
+            old,
+            new,
+        }
+    }
+
+    #[test]
+    fn filter_and_rollback() {
+        let entries = vec![
+            entry(100, "steve", Vec3(0, 10, 0), 1, 2),
+            entry(200, "steve", Vec3(0, 10, 0), 2, 3),
+            entry(200, "alex", Vec3(1, 10, 0), 0, 4),
+            entry(300, "steve", Vec3(20, 10, 0), 0, 5),
+            entry(400, "steve", Vec3(1, 10, 0), 4, 6),
+        ];
+
+        let filter = JournalFilter {
+            player: Some("steve".to_owned()),
+            since: 150,
+            area: Some((Vec3(0, 10, 0), 5)),
+        };
+
+        let matching = entries
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect::<Vec<_>>();
+
+        assert_eq!(matching.len(), 2);
+
+        let edits = rollback_edits(&matching);
+        assert_eq!(edits.len(), 2);
+        assert_eq!((edits[0].voxel.clone(), edits[0].id), (Vec3(0, 10, 0), 2));
+        assert_eq!((edits[1].voxel.clone(), edits[1].id), (Vec3(1, 10, 0), 4));
+    }
+
+    #[test]
+    fn query_from_since() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("minejs-journal-{}.jsonl", std::process::id()));
+
+        let mut lines = (0..50)
+            .map(|i| serde_json::to_string(&entry(i * 10, "steve", Vec3(i as i32, 0, 0), 0, 1)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        lines.insert(30, "{\"time\": 29".to_owned());
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let journal = Journal::new(Some(path.clone()));
+
+        for since in [0, 5, 250, 290, 300, 490, 1000] {
+            let entries = journal
+                .query(&JournalFilter {
+                    since,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let expected = (0..50).filter(|i| i * 10 >= since).count();
+            assert_eq!(entries.len(), expected);
+            assert!(entries.iter().all(|entry| entry.time >= since));
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
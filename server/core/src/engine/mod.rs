@@ -1,12 +1,23 @@
 pub mod astar;
+pub mod backup;
 pub mod chunk;
 pub mod chunks;
 pub mod clock;
+pub mod commands;
 pub mod config;
+pub mod edits;
 pub mod entities;
+pub mod fluids;
+pub mod history;
+pub mod journal;
 pub mod kdtree;
+pub mod permissions;
 pub mod physics;
 pub mod players;
+pub mod region;
 pub mod registry;
+pub mod schematic;
 pub mod space;
+pub mod ticks;
 pub mod world;
+pub mod writer;
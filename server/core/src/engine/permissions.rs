@@ -0,0 +1,242 @@
+use log::error;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use super::chunk::unique_path;
+
+/// What a player is allowed to do in a world, each role allowing everything of the ones before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    /// Can walk around and chat
+    Visitor,
+    /// Can edit blocks
+    #[default]
+    Builder,
+    /// Can change the world's settings, run administrative commands and manage roles
+    Operator,
+}
+
+impl Role {
+    /// Parse a role from its name, as typed in chat
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "visitor" => Some(Role::Visitor),
+            "builder" => Some(Role::Builder),
+            "operator" | "op" => Some(Role::Operator),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Visitor => "visitor",
+            Role::Builder => "builder",
+            Role::Operator => "operator",
+        }
+    }
+}
+
+/// Who can change what in a world, configured per world through `permissions` in `worlds.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionConfig {
+    /// Role of players that weren't granted any
+    #[serde(default)]
+    pub default_role: Role,
+
+    /// Names of players who are always operators, whatever the roles granted in game
+    #[serde(default)]
+    pub operators: Vec<String>,
+}
+
+/// Resource of the roles granted to players by name, stored in the world's data folder
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Permissions {
+    roles: BTreeMap<String, Role>,
+
+    #[serde(skip)]
+    config: PermissionConfig,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    /// Whether a broken file couldn't be moved aside, in which case it's never overwritten
+    #[serde(skip)]
+    locked: bool,
+}
+
+impl Permissions {
+    /// Load the roles stored at `path`, or start with none granted if there are none or they
+    /// can't be read. Without a path, granted roles only last as long as the world runs.
+    ///
+    /// Roles that can't be read are moved aside into `permissions-broken-*.json` to be fixed by
+    /// hand, or never overwritten if they can't be moved.
+    pub fn load(config: PermissionConfig, path: Option<PathBuf>) -> Self {
+        let mut permissions = match path.as_ref().and_then(|path| File::open(path).ok()) {
+            Some(file) => serde_json::from_reader::<_, Self>(file).unwrap_or_else(|e| {
+                let path = path.as_ref().unwrap();
+                let folder = path.parent().unwrap_or_else(|| Path::new("."));
+                let aside = unique_path(folder, "permissions-broken", "json");
+
+                match fs::rename(path, &aside) {
+                    Ok(()) => {
+                        error!(
+                            "Broken permissions at {:?} ({}) moved into {:?}, granting no roles...",
+                            path, e, aside
+                        );
+
+                        Self::default()
+                    }
+                    Err(re) => {
+                        error!(
+                            "Broken permissions at {:?} ({}) can't be moved ({}), saving none...",
+                            path, e, re
+                        );
+
+                        Self {
+                            locked: true,
+                            ..Self::default()
+                        }
+                    }
+                }
+            }),
+            None => Self::default(),
+        };
+
+        permissions.config = config;
+        permissions.path = path;
+
+        permissions
+    }
+
+    /// Role of a player by name, players without a name having the default role
+    pub fn role_of(&self, name: Option<&str>) -> Role {
+        let name = match name {
+            Some(name) => name,
+            None => return self.config.default_role,
+        };
+
+        if self.is_configured_operator(name) {
+            return Role::Operator;
+        }
+
+        self.roles
+            .get(name)
+            .copied()
+            .unwrap_or(self.config.default_role)
+    }
+
+    /// Whether a player by name has at least the given role
+    pub fn allows(&self, name: Option<&str>, role: Role) -> bool {
+        self.role_of(name) >= role
+    }
+
+    /// Grant a role to a player by name, saving the roles
+    pub fn grant(&mut self, name: &str, role: Role) -> io::Result<()> {
+        self.roles.insert(name.to_owned(), role);
+        self.save()
+    }
+
+    /// Revoke the role granted to a player by name, saving the roles. Returns the revoked role.
+    pub fn revoke(&mut self, name: &str) -> io::Result<Option<Role>> {
+        let role = self.roles.remove(name);
+
+        if role.is_some() {
+            self.save()?;
+        }
+
+        Ok(role)
+    }
+
+    /// Whether a player by name is an operator through the world's config, which can't be revoked
+    pub fn is_configured_operator(&self, name: &str) -> bool {
+        self.config.operators.iter().any(|op| op == name)
+    }
+
+    /// Write the roles to disk, to a temporary file first to never leave a broken file
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if self.locked {
+            return Err(io::Error::other(format!(
+                "{:?} can't be read, fix it and restart the world",
+                path
+            )));
+        }
+
+        let tmp = path.with_extension("json.tmp");
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+
+        fs::rename(tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_by_name() {
+        let mut permissions = Permissions::load(
+            PermissionConfig {
+                default_role: Role::Visitor,
+                operators: vec!["admin".to_owned()],
+            },
+            None,
+        );
+
+        assert_eq!(permissions.role_of(None), Role::Visitor);
+        assert_eq!(permissions.role_of(Some("admin")), Role::Operator);
+
+        permissions.grant("steve", Role::Builder).unwrap();
+        assert!(permissions.allows(Some("steve"), Role::Builder));
+        assert!(!permissions.allows(Some("steve"), Role::Operator));
+
+        assert_eq!(permissions.revoke("steve").unwrap(), Some(Role::Builder));
+        assert_eq!(permissions.role_of(Some("steve")), Role::Visitor);
+
+        assert_eq!(permissions.revoke("admin").unwrap(), None);
+        assert_eq!(permissions.role_of(Some("admin")), Role::Operator);
+
+        // malformed roles are moved aside instead of stopping the world or being overwritten
+        let mut folder = std::env::temp_dir();
+        folder.push(format!("minejs-permissions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let path = folder.join("permissions.json");
+        fs::write(&path, "{ \"roles\": ").unwrap();
+
+        let mut permissions = Permissions::load(PermissionConfig::default(), Some(path.clone()));
+        assert_eq!(permissions.role_of(Some("steve")), Role::Builder);
+
+        permissions.grant("steve", Role::Operator).unwrap();
+
+        let broken = fs::read_dir(&folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|p| *p != path)
+            .collect::<Vec<_>>();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(fs::read_to_string(&broken[0]).unwrap(), "{ \"roles\": ");
+
+        let permissions = Permissions::load(PermissionConfig::default(), Some(path));
+        assert_eq!(permissions.role_of(Some("steve")), Role::Operator);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
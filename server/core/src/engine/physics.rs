@@ -67,13 +67,13 @@ impl Physics {
 
         // skip bodies if static or no velocity/forces/impulses
         let local_no_grav = no_gravity || approx_equals(&b.gravity_multiplier, &0.0);
-        if self.body_asleep(b, &dt, &local_no_grav, &test_solid) {
+        if self.body_asleep(b, &dt, &local_no_grav, test_solid) {
             return;
         }
         b.sleep_frame_count -= 1;
 
         // check if under water, if so apply buoyancy and drag forces
-        self.apply_fluid_forces(b, &test_fluid);
+        self.apply_fluid_forces(b, test_fluid);
 
         // semi-implicit Euler integration
 
@@ -129,12 +129,12 @@ impl Physics {
         };
 
         // sweeps aabb along dx and accounts for collisions
-        self.process_collisions(&mut b.aabb, &dx, &mut b.resting, &test_solid);
+        self.process_collisions(&mut b.aabb, &dx, &mut b.resting, test_solid);
 
         // if autostep, and on ground, run collisions again with stepped up aabb
         if b.auto_step {
             let mut tmp_box = tmp_box.unwrap();
-            self.try_auto_stepping(b, &mut tmp_box, &dx, &test_solid);
+            self.try_auto_stepping(b, &mut tmp_box, &dx, test_solid);
         }
 
         let mut impacts = Vec3::default();
@@ -408,3 +408,75 @@ impl Physics {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::comp::brain::{Brain, BrainOptions};
+
+    fn physics(fluid_density: f32) -> Physics {
+        Physics::new(PhysicsOptions {
+            gravity: Vec3(0.0, -24.0, 0.0),
+            min_bounce_impulse: 0.1,
+            air_drag: 0.1,
+            fluid_drag: 0.4,
+            fluid_density,
+        })
+    }
+
+    fn body() -> RigidBody {
+        let aabb = Aabb::new(&Vec3(0.0, 5.0, 0.0), &Vec3(1.0, 1.0, 1.0));
+        RigidBody::new(aabb, 0.5, 1.0, 0.0, 0.0, 1.0, false)
+    }
+
+    /// Neither solids nor fluids anywhere
+    fn test_empty(_: i32, _: i32, _: i32) -> bool {
+        false
+    }
+
+    /// Fluid up to `vy = 10`, well above the bodies
+    fn test_fluid(_: i32, vy: i32, _: i32) -> bool {
+        vy < 10
+    }
+
+    #[test]
+    fn buoyancy_in_fluids() {
+        let physics = physics(2.0);
+
+        let mut floating = body();
+        let mut falling = body();
+
+        for _ in 0..10 {
+            physics.iterate_body(&mut floating, 0.05, &test_empty, &test_fluid);
+            physics.iterate_body(&mut falling, 0.05, &test_empty, &test_empty);
+        }
+
+        assert!(floating.in_fluid);
+        assert!((floating.ratio_in_fluid - 1.0).abs() < f32::EPSILON);
+        assert!(floating.get_position().1 > 5.0);
+
+        assert!(!falling.in_fluid);
+        assert!(falling.get_position().1 < 5.0);
+    }
+
+    #[test]
+    fn swim_up_when_submerged() {
+        // fluid too light to float in
+        let physics = physics(0.5);
+        let brain = Brain::new(BrainOptions::default());
+
+        let mut swimming = body();
+        let mut sinking = body();
+
+        for _ in 0..10 {
+            brain.swim(&mut swimming);
+
+            physics.iterate_body(&mut swimming, 0.05, &test_empty, &test_fluid);
+            physics.iterate_body(&mut sinking, 0.05, &test_empty, &test_fluid);
+        }
+
+        assert!(swimming.get_position().1 > 5.0);
+        assert!(sinking.get_position().1 < 5.0);
+    }
+}
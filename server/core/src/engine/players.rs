@@ -7,6 +7,7 @@ use actix::Recipient;
 use specs::Entity;
 
 use super::super::network::{message, models::messages};
+use super::edits::EditLimiter;
 
 use server_common::vec::Vec2;
 
@@ -21,6 +22,7 @@ pub struct Player {
     pub name: Option<String>,
     pub addr: Recipient<message::Message>,
     pub requested_chunks: VecDeque<Vec2<i32>>,
+    pub edits: EditLimiter,
 }
 
 /// Resource to store all server-side players in a HashMap
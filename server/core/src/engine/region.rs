@@ -0,0 +1,488 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use libflate::zlib::{Decoder, Encoder};
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use server_common::vec::Vec2;
+
+/// Number of chunks along each side of a region
+pub const REGION_SIZE: i32 = 32;
+
+/// Region files are allocated in sectors of this many bytes
+const SECTOR_SIZE: u64 = 4096;
+
+/// Size of one offset table entry: sector offset (u32) + byte length (u32)
+const ENTRY_SIZE: u64 = 8;
+
+/// Offset table of `REGION_SIZE * REGION_SIZE` entries, rounded up to whole sectors
+const HEADER_SECTORS: u64 =
+    (REGION_SIZE as u64 * REGION_SIZE as u64 * ENTRY_SIZE).div_ceil(SECTOR_SIZE);
+
+const REGION_EXTENSION: &str = "region";
+
+/// Raw data of a single chunk stored in a region
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkData {
+    pub needs_propagation: bool,
+    pub voxels: Vec<u32>,
+    pub lights: Vec<u32>,
+    pub height_map: Vec<u32>,
+}
+
+impl ChunkData {
+    /// Serialize the chunk data into a zlib compressed binary blob
+    ///
+    /// Layout before compression: `flags (u8)`, then for voxels, lights and height map
+    /// each a `length (u32)` followed by `length` little-endian u32's.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let total = 1 + 4 * 3 + (self.voxels.len() + self.lights.len() + self.height_map.len()) * 4;
+        let mut bytes = Vec::with_capacity(total);
+
+        bytes.push(self.needs_propagation as u8);
+
+        for data in [&self.voxels, &self.lights, &self.height_map].iter() {
+            let mut len = [0; 4];
+            LittleEndian::write_u32(&mut len, data.len() as u32);
+            bytes.extend_from_slice(&len);
+
+            let start = bytes.len();
+            bytes.resize(start + data.len() * 4, 0);
+            LittleEndian::write_u32_into(data, &mut bytes[start..]);
+        }
+
+        let mut encoder = Encoder::new(Vec::new())?;
+        encoder.write_all(&bytes)?;
+        encoder.finish().into_result()
+    }
+
+    /// Deserialize a zlib compressed binary blob created by `ChunkData::encode`
+    pub fn decode(compressed: &[u8]) -> io::Result<Self> {
+        let mut decoder = Decoder::new(compressed)?;
+        let mut bytes = Vec::new();
+        decoder.read_to_end(&mut bytes)?;
+
+        if bytes.is_empty() {
+            return Err(invalid_data("empty chunk data"));
+        }
+
+        let needs_propagation = bytes[0] != 0;
+        let mut cursor = 1;
+
+        let mut read_array = || -> io::Result<Vec<u32>> {
+            if bytes.len() < cursor + 4 {
+                return Err(invalid_data("truncated chunk data"));
+            }
+
+            let len = LittleEndian::read_u32(&bytes[cursor..cursor + 4]) as usize;
+            cursor += 4;
+
+            let end = cursor + len * 4;
+            if bytes.len() < end {
+                return Err(invalid_data("truncated chunk data"));
+            }
+
+            let mut data = vec![0; len];
+            LittleEndian::read_u32_into(&bytes[cursor..end], &mut data);
+            cursor = end;
+
+            Ok(data)
+        };
+
+        let voxels = read_array()?;
+        let lights = read_array()?;
+        let height_map = read_array()?;
+
+        Ok(Self {
+            needs_propagation,
+            voxels,
+            lights,
+            height_map,
+        })
+    }
+}
+
+/// A file storing up to `REGION_SIZE * REGION_SIZE` chunks.
+///
+/// The file starts with an offset table (sector offset and byte length per chunk),
+/// followed by the compressed chunk data aligned to `SECTOR_SIZE` sectors.
+#[derive(Debug)]
+pub struct RegionFile {
+    pub coords: Vec2<i32>,
+
+    file: File,
+    table: Vec<(u32, u32)>,
+}
+
+impl RegionFile {
+    /// Open the region file containing the chunk at `coords` within `folder`.
+    ///
+    /// Returns `None` if the region file doesn't exist and `create` is false.
+    pub fn open(folder: &Path, coords: &Vec2<i32>, create: bool) -> io::Result<Option<Self>> {
+        let region = RegionFile::region_coords(coords);
+        RegionFile::open_region(folder, &region, create)
+    }
+
+    /// Open a region file by region coordinates.
+    pub fn open_region(
+        folder: &Path,
+        region: &Vec2<i32>,
+        create: bool,
+    ) -> io::Result<Option<Self>> {
+        RegionFile::open_path(&RegionFile::path(folder, region), region, create)
+    }
+
+    /// Open a region file at an arbitrary path, used for temporary region files.
+    fn open_path(path: &Path, region: &Vec2<i32>, create: bool) -> io::Result<Option<Self>> {
+        if !create && !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .open(path)?;
+
+        let entries = (REGION_SIZE * REGION_SIZE) as usize;
+        let header_len = HEADER_SECTORS * SECTOR_SIZE;
+        let file_len = file.metadata()?.len();
+
+        let mut table = vec![(0, 0); entries];
+
+        if file_len == 0 {
+            file.set_len(header_len)?;
+        } else if file_len < header_len {
+            return Err(invalid_data("region header is truncated"));
+        } else {
+            let mut header = vec![0; entries * ENTRY_SIZE as usize];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut header)?;
+
+            for (i, entry) in table.iter_mut().enumerate() {
+                let at = i * ENTRY_SIZE as usize;
+                entry.0 = LittleEndian::read_u32(&header[at..at + 4]);
+                entry.1 = LittleEndian::read_u32(&header[at + 4..at + 8]);
+            }
+        }
+
+        Ok(Some(Self {
+            coords: region.to_owned(),
+            file,
+            table,
+        }))
+    }
+
+    /// Rewrite a region file with `updates` applied on top of its existing chunks.
+    ///
+    /// The region is written into a temporary file first, which then replaces the original,
+    /// so a crash mid-write never leaves a half written region behind. This also compacts
+    /// the sectors left unused by chunks that grew. Fails without touching the region if any of
+    /// its other entries can't be read anymore, so they're never dropped silently.
+    pub fn write_atomic(
+        folder: &Path,
+        region: &Vec2<i32>,
+        updates: Vec<(Vec2<i32>, Vec<u8>)>,
+    ) -> io::Result<()> {
+        let path = RegionFile::path(folder, region);
+        let tmp = path.with_extension(format!("{}.tmp", REGION_EXTENSION));
+
+        let mut chunks = vec![];
+
+        if let Some(mut old) = RegionFile::open_region(folder, region, false)? {
+            for coords in old.chunks() {
+                if updates.iter().any(|(c, _)| *c == coords) {
+                    continue;
+                }
+
+                match old.read(&coords) {
+                    Ok(Some(bytes)) => chunks.push((coords, bytes)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        return Err(io::Error::new(
+                            e.kind(),
+                            format!("chunk {:?} can't be read: {}", coords, e),
+                        ))
+                    }
+                }
+            }
+        }
+
+        chunks.extend(updates);
+
+        if tmp.exists() {
+            fs::remove_file(&tmp)?;
+        }
+
+        let mut new = RegionFile::open_path(&tmp, region, true)?.unwrap();
+
+        for (coords, bytes) in chunks.iter() {
+            new.write(coords, bytes)?;
+        }
+
+        new.file.sync_all()?;
+        drop(new);
+
+        fs::rename(tmp, path)
+    }
+
+    /// Map chunk coordinates to the coordinates of the region containing it
+    pub fn region_coords(Vec2(cx, cz): &Vec2<i32>) -> Vec2<i32> {
+        Vec2(cx.div_euclid(REGION_SIZE), cz.div_euclid(REGION_SIZE))
+    }
+
+    /// Path to the region file of region coordinates
+    pub fn path(folder: &Path, Vec2(rx, rz): &Vec2<i32>) -> PathBuf {
+        let mut path = folder.to_path_buf();
+        path.push(format!("r_{}_{}.{}", rx, rz, REGION_EXTENSION));
+        path
+    }
+
+    /// List the region coordinates of all region files within a folder
+    pub fn list(folder: &Path) -> io::Result<Vec<Vec2<i32>>> {
+        let mut regions = vec![];
+
+        for entry in fs::read_dir(folder)?.flatten() {
+            let path = entry.path();
+
+            if path.extension().and_then(|e| e.to_str()) != Some(REGION_EXTENSION) {
+                continue;
+            }
+
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let parts = stem.split('_').collect::<Vec<_>>();
+
+            if parts.len() != 3 || parts[0] != "r" {
+                continue;
+            }
+
+            if let (Ok(rx), Ok(rz)) = (parts[1].parse(), parts[2].parse()) {
+                regions.push(Vec2(rx, rz));
+            }
+        }
+
+        Ok(regions)
+    }
+
+    /// Coordinates of all chunks stored in this region
+    pub fn chunks(&self) -> Vec<Vec2<i32>> {
+        let Vec2(rx, rz) = self.coords;
+
+        self.table
+            .iter()
+            .enumerate()
+            .filter(|(_, &(offset, _))| offset != 0)
+            .map(|(i, _)| {
+                let i = i as i32;
+                Vec2(
+                    rx * REGION_SIZE + i % REGION_SIZE,
+                    rz * REGION_SIZE + i / REGION_SIZE,
+                )
+            })
+            .collect()
+    }
+
+    /// Whether a chunk is stored within this region
+    pub fn contains(&self, coords: &Vec2<i32>) -> bool {
+        self.table[RegionFile::index(coords)].0 != 0
+    }
+
+    /// Read the compressed data of a chunk, `None` if not stored.
+    pub fn read(&mut self, coords: &Vec2<i32>) -> io::Result<Option<Vec<u8>>> {
+        let (offset, length) = self.table[RegionFile::index(coords)];
+
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let start = offset as u64 * SECTOR_SIZE;
+        if start + length as u64 > self.file.metadata()?.len() {
+            return Err(invalid_data("chunk data is out of region bounds"));
+        }
+
+        let mut data = vec![0; length as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(Some(data))
+    }
+
+    /// Write the compressed data of a chunk.
+    ///
+    /// The data is always appended to the end of the file and synced before the offset table
+    /// points to it, so a crash mid-write leaves the chunk's old data in place. The sectors left
+    /// behind are reclaimed the next time `RegionFile::write_atomic` compacts the region.
+    pub fn write(&mut self, coords: &Vec2<i32>, data: &[u8]) -> io::Result<()> {
+        let index = RegionFile::index(coords);
+
+        let sectors = sectors_for(data.len() as u64);
+        let file_len = self.file.metadata()?.len();
+        let offset = sectors_for(file_len).max(HEADER_SECTORS);
+
+        self.file.seek(SeekFrom::Start(offset * SECTOR_SIZE))?;
+        self.file.write_all(data)?;
+        self.file.set_len((offset + sectors) * SECTOR_SIZE)?;
+        self.file.sync_data()?;
+
+        self.set_entry(index, offset as u32, data.len() as u32)
+    }
+
+    /// Remove a chunk from the region's offset table.
+    pub fn remove(&mut self, coords: &Vec2<i32>) -> io::Result<()> {
+        self.set_entry(RegionFile::index(coords), 0, 0)
+    }
+
+    fn set_entry(&mut self, index: usize, offset: u32, length: u32) -> io::Result<()> {
+        let mut entry = [0; ENTRY_SIZE as usize];
+        LittleEndian::write_u32(&mut entry[0..4], offset);
+        LittleEndian::write_u32(&mut entry[4..8], length);
+
+        self.file.seek(SeekFrom::Start(index as u64 * ENTRY_SIZE))?;
+        self.file.write_all(&entry)?;
+
+        self.table[index] = (offset, length);
+
+        Ok(())
+    }
+
+    /// Index of a chunk within the offset table
+    fn index(Vec2(cx, cz): &Vec2<i32>) -> usize {
+        (cx.rem_euclid(REGION_SIZE) + cz.rem_euclid(REGION_SIZE) * REGION_SIZE) as usize
+    }
+}
+
+fn sectors_for(bytes: u64) -> u64 {
+    bytes.div_ceil(SECTOR_SIZE)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let mut folder = std::env::temp_dir();
+        folder.push(format!("minejs-region-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn sample(len: usize, seed: u32) -> ChunkData {
+        ChunkData {
+            needs_propagation: seed > 4,
+            voxels: (0..len as u32).map(|i| i.wrapping_mul(seed)).collect(),
+            lights: (0..len as u32).map(|i| i ^ seed).collect(),
+            height_map: vec![seed; 16],
+        }
+    }
+
+    #[test]
+    fn chunk_data_round_trip() {
+        let data = sample(1000, 7);
+        let encoded = data.encode().unwrap();
+        assert_eq!(ChunkData::decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn corrupted_chunk_data() {
+        assert!(ChunkData::decode(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn region_coordinates() {
+        assert_eq!(RegionFile::region_coords(&Vec2(0, 31)), Vec2(0, 0));
+        assert_eq!(RegionFile::region_coords(&Vec2(32, -1)), Vec2(1, -1));
+        assert_eq!(RegionFile::region_coords(&Vec2(-33, -32)), Vec2(-2, -1));
+    }
+
+    #[test]
+    fn region_read_write() {
+        let folder = temp_folder("read-write");
+
+        let a = sample(100, 3).encode().unwrap();
+        let b = sample(20000, 5).encode().unwrap();
+        let c = sample(50000, 11).encode().unwrap();
+
+        {
+            let mut region = RegionFile::open(&folder, &Vec2(-1, 2), true)
+                .unwrap()
+                .unwrap();
+            region.write(&Vec2(-1, 2), &a).unwrap();
+            region.write(&Vec2(-2, 3), &b).unwrap();
+
+            // never written over the old data, even when it would fit
+            let old = region.table[RegionFile::index(&Vec2(-1, 2))];
+            region.write(&Vec2(-1, 2), &c).unwrap();
+            assert!(region.table[RegionFile::index(&Vec2(-1, 2))].0 > old.0);
+        }
+
+        let mut region = RegionFile::open(&folder, &Vec2(-32, 31), false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(region.read(&Vec2(-1, 2)).unwrap().unwrap(), c);
+        assert_eq!(region.read(&Vec2(-2, 3)).unwrap().unwrap(), b);
+        assert_eq!(region.read(&Vec2(-3, 3)).unwrap(), None);
+
+        let mut chunks = region.chunks();
+        chunks.sort_by_key(|Vec2(x, z)| (*x, *z));
+        assert_eq!(chunks, vec![Vec2(-2, 3), Vec2(-1, 2)]);
+
+        region.remove(&Vec2(-2, 3)).unwrap();
+        assert!(!region.contains(&Vec2(-2, 3)));
+
+        assert_eq!(RegionFile::list(&folder).unwrap(), vec![Vec2(-1, 0)]);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn region_atomic_write() {
+        let folder = temp_folder("atomic");
+
+        let a = sample(100, 3).encode().unwrap();
+        let b = sample(20000, 5).encode().unwrap();
+        let c = sample(300, 7).encode().unwrap();
+
+        RegionFile::write_atomic(
+            &folder,
+            &Vec2(0, 0),
+            vec![(Vec2(1, 1), a.clone()), (Vec2(2, 2), b.clone())],
+        )
+        .unwrap();
+        RegionFile::write_atomic(&folder, &Vec2(0, 0), vec![(Vec2(2, 2), c.clone())]).unwrap();
+
+        let mut region = RegionFile::open_region(&folder, &Vec2(0, 0), false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(region.read(&Vec2(1, 1)).unwrap().unwrap(), a);
+        assert_eq!(region.read(&Vec2(2, 2)).unwrap().unwrap(), c);
+        assert_eq!(RegionFile::list(&folder).unwrap(), vec![Vec2(0, 0)]);
+
+        // an unreadable entry aborts the rewrite instead of being dropped
+        region
+            .set_entry(RegionFile::index(&Vec2(1, 1)), 1000, 100)
+            .unwrap();
+        drop(region);
+
+        assert!(RegionFile::write_atomic(&folder, &Vec2(0, 0), vec![(Vec2(2, 2), a)]).is_err());
+
+        let mut region = RegionFile::open_region(&folder, &Vec2(0, 0), false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(region.table[RegionFile::index(&Vec2(1, 1))], (1000, 100));
+        assert_eq!(region.read(&Vec2(2, 2)).unwrap().unwrap(), c);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -92,40 +92,24 @@ impl Registry {
                 for (side, img_src) in textures.as_object().unwrap().iter() {
                     let img_src_str = img_src.as_str().unwrap();
 
-                    let image = if img_src_str.ends_with(".png") {
-                        let path =
-                            format!("assets/textures/packs/{}/blocks/{}", pack_name, img_src_str);
-                        image::open(&path).unwrap_or_else(|_| panic!("Texture not found: {}", path))
-                    } else {
-                        // texture data
-                        let texture_data: serde_json::Value = serde_json::from_reader(
-                            File::open(format!("assets/textures/procedural/{}", img_src_str))
-                                .unwrap(),
-                        )
-                        .unwrap();
-
-                        let color_vec = texture_data["color"].as_array().unwrap().as_slice();
-
-                        let color_r = (color_vec[0].as_f64().unwrap() * 255.0) as u8;
-                        let color_g = (color_vec[1].as_f64().unwrap() * 255.0) as u8;
-                        let color_b = (color_vec[2].as_f64().unwrap() * 255.0) as u8;
-
-                        let imgbuf = image::ImageBuffer::from_pixel(
-                            16,
-                            16,
-                            image::Rgb([color_r, color_g, color_b]),
-                        );
-
-                        image::DynamicImage::ImageRgb8(imgbuf)
-                    };
-
-                    texture_map.insert(img_src_str.to_owned(), image);
+                    texture_map
+                        .entry(img_src_str.to_owned())
+                        .or_insert_with(|| load_texture(pack_name, img_src_str));
                     textures_hash.insert(side.to_owned(), img_src_str.to_owned());
                 }
             }
 
             let mut new_block: Block = serde_json::from_value(block_json).unwrap();
             new_block.textures = textures_hash;
+
+            for stage in new_block.stages.iter() {
+                for img_src in stage.textures.values() {
+                    texture_map
+                        .entry(img_src.to_owned())
+                        .or_insert_with(|| load_texture(pack_name, img_src));
+                }
+            }
+
             let id = id.parse::<u32>().unwrap();
             name_map.insert(new_block.name.clone(), id);
             blocks.insert(id, new_block);
@@ -276,6 +260,26 @@ impl Registry {
         self.get_uv_map(self.get_block_by_name(name))
     }
 
+    /// Get block texture of a growth stage by id, falling back to the block's own textures
+    pub fn get_texture_by_stage(&self, id: u32, stage: u32) -> &HashMap<String, String> {
+        let block = self.get_block_by_id(id);
+
+        match block.stages.get(stage as usize) {
+            Some(block_stage) if !block_stage.textures.is_empty() => &block_stage.textures,
+            _ => &block.textures,
+        }
+    }
+
+    /// Get block UV of a growth stage by id, falling back to the block's own UV
+    pub fn get_uv_by_stage(&self, id: u32, stage: u32) -> HashMap<String, &UV> {
+        self.get_uv_map_of(self.get_texture_by_stage(id, stage))
+    }
+
+    /// Get the last growth stage of a block, 0 if it doesn't grow
+    pub fn get_max_stage(&self, id: u32) -> u32 {
+        (self.get_block_by_id(id).stages.len() as u32).saturating_sub(1)
+    }
+
     /// Check if block is air by id
     pub fn is_air(&self, id: u32) -> bool {
         self.get_block_by_id(id).name == "Air"
@@ -313,6 +317,23 @@ impl Registry {
         self.get_block_by_id(id)
     }
 
+    /// Check if a block of this name exists
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name_map.contains_key(name)
+    }
+
+    /// Find the name of a block ignoring case
+    pub fn find_name(&self, name: &str) -> Option<&str> {
+        if let Some((name, _)) = self.name_map.get_key_value(name) {
+            return Some(name);
+        }
+
+        self.name_map
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(name))
+            .map(|key| key.as_str())
+    }
+
     /// Get block id by name
     pub fn get_id_by_name(&self, name: &str) -> &u32 {
         self.name_map
@@ -322,9 +343,14 @@ impl Registry {
 
     /// Get UV map by block
     pub fn get_uv_map(&self, block: &Block) -> HashMap<String, &UV> {
+        self.get_uv_map_of(&block.textures)
+    }
+
+    /// Get UV map by textures
+    pub fn get_uv_map_of(&self, textures: &HashMap<String, String>) -> HashMap<String, &UV> {
         let mut uv_map = HashMap::new();
 
-        for source in block.textures.values().into_iter() {
+        for source in textures.values().into_iter() {
             let uv = self
                 .ranges
                 .get(source)
@@ -382,6 +408,91 @@ pub fn get_texture_type(texture: &HashMap<String, String>) -> &str {
     }
 }
 
+#[cfg(test)]
+impl Registry {
+    /// Create a registry out of some blocks without any texture pack, each texture getting
+    /// its own UV range
+    pub fn from_blocks(blocks: Vec<(u32, Block)>) -> Self {
+        let mut sources = blocks
+            .iter()
+            .flat_map(|(_, block)| {
+                block.textures.values().chain(
+                    block
+                        .stages
+                        .iter()
+                        .flat_map(|stage| stage.textures.values()),
+                )
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        sources.sort();
+        sources.dedup();
+
+        let ranges = sources
+            .into_iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let start_u = i as f32 / 16.0;
+                let uv = UV {
+                    start_u,
+                    end_u: start_u + 1.0 / 16.0,
+                    start_v: 1.0,
+                    end_v: 0.0,
+                };
+
+                (source, uv)
+            })
+            .collect();
+
+        Self {
+            atlas: image::ImageBuffer::new(1, 1),
+            ranges,
+            name_map: blocks.iter().map(|(id, b)| (b.name.clone(), *id)).collect(),
+            blocks: blocks.into_iter().collect(),
+            uv_side_count: 16,
+            uv_texture_size: 16,
+        }
+    }
+
+    /// Load a block of `assets/metadata/blocks` merged with its base
+    pub fn load_block(file: &str) -> Block {
+        let folder = concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets/metadata/blocks");
+        let read = |file: &str| -> serde_json::Value {
+            serde_json::from_reader(File::open(format!("{}/{}", folder, file)).unwrap()).unwrap()
+        };
+
+        let mut block_json = read(file);
+        let base = read(block_json["base"].as_str().unwrap());
+        json::merge(&mut block_json, &base, false);
+
+        serde_json::from_value(block_json).unwrap()
+    }
+}
+
+/// Load a texture of a pack, either an image or procedural texture data
+fn load_texture(pack_name: &str, img_src: &str) -> image::DynamicImage {
+    if img_src.ends_with(".png") {
+        let path = format!("assets/textures/packs/{}/blocks/{}", pack_name, img_src);
+        return image::open(&path).unwrap_or_else(|_| panic!("Texture not found: {}", path));
+    }
+
+    // texture data
+    let texture_data: serde_json::Value = serde_json::from_reader(
+        File::open(format!("assets/textures/procedural/{}", img_src)).unwrap(),
+    )
+    .unwrap();
+
+    let color_vec = texture_data["color"].as_array().unwrap().as_slice();
+
+    let color_r = (color_vec[0].as_f64().unwrap() * 255.0) as u8;
+    let color_g = (color_vec[1].as_f64().unwrap() * 255.0) as u8;
+    let color_b = (color_vec[2].as_f64().unwrap() * 255.0) as u8;
+
+    let imgbuf = image::ImageBuffer::from_pixel(16, 16, image::Rgb([color_r, color_g, color_b]));
+
+    image::DynamicImage::ImageRgb8(imgbuf)
+}
+
 /// Fixing texture bleeding with the
 /// [Half-texel edge correction method](http://drilian.com/2008/11/25/understanding-half-pixel-and-half-texel-offsets/)
 fn fix_texture_bleeding(
@@ -395,3 +506,31 @@ fn fix_texture_bleeding(
         end_v + offset,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textures_per_stage() {
+        let registry = Registry::from_blocks(vec![(103, Registry::load_block("wheat.json"))]);
+
+        assert_eq!(registry.get_max_stage(103), 2);
+
+        // the first stage keeps the block's own textures, as do stages past the last
+        for (stage, source) in [
+            (0, "wheat-sprout.json"),
+            (1, "wheat-young.json"),
+            (2, "wheat-ripe.json"),
+            (3, "wheat-sprout.json"),
+        ] {
+            let texture = registry.get_texture_by_stage(103, stage);
+            assert_eq!(texture["one"], source);
+            assert_eq!(get_texture_type(texture), "x");
+
+            let uv_map = registry.get_uv_by_stage(103, stage);
+            assert_eq!(uv_map.len(), 1);
+            assert_eq!(uv_map[source].start_u, registry.ranges[source].start_u);
+        }
+    }
+}
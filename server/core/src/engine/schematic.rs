@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use server_common::vec::Vec3;
+
+use crate::gen::blocks::{BlockRotation, Blocks};
+
+use super::{
+    edits::{Cuboid, VoxelEdit},
+    registry::Registry,
+};
+
+/// How a schematic is flipped then turned when pasted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Transform {
+    /// Quarter turns around the y axis
+    pub turns: u32,
+    pub mirror_x: bool,
+    pub mirror_z: bool,
+}
+
+impl Transform {
+    /// Parse a rotation in degrees and a mirroring of `x`, `z` or `xz`, as typed in commands
+    pub fn parse(degrees: Option<i32>, mirror: Option<&str>) -> Result<Self, String> {
+        let degrees = degrees.unwrap_or(0);
+
+        if degrees % 90 != 0 {
+            return Err(format!("{} isn't a multiple of 90 degrees", degrees));
+        }
+
+        let (mirror_x, mirror_z) = match mirror.map(|m| m.to_lowercase()).as_deref() {
+            None | Some("none") => (false, false),
+            Some("x") => (true, false),
+            Some("z") => (false, true),
+            Some("xz") | Some("zx") => (true, true),
+            Some(other) => return Err(format!("Unknown mirroring \"{}\", use x, z or xz", other)),
+        };
+
+        Ok(Self {
+            turns: degrees.rem_euclid(360) as u32 / 90,
+            mirror_x,
+            mirror_z,
+        })
+    }
+
+    /// Size of a schematic once transformed
+    pub fn size(&self, size: &Vec3<i32>) -> Vec3<i32> {
+        if self.turns % 2 == 1 {
+            Vec3(size.2, size.1, size.0)
+        } else {
+            size.clone()
+        }
+    }
+
+    /// Position of a voxel relative to the minimum corner of a schematic of `size`, once
+    /// transformed
+    pub fn position(&self, position: &Vec3<i32>, size: &Vec3<i32>) -> Vec3<i32> {
+        let Vec3(mut x, y, mut z) = position.clone();
+        let Vec3(mut size_x, _, mut size_z) = size.clone();
+
+        if self.mirror_x {
+            x = size_x - 1 - x;
+        }
+
+        if self.mirror_z {
+            z = size_z - 1 - z;
+        }
+
+        // the same way `BlockRotation` turns around the y axis
+        for _ in 0..self.turns % 4 {
+            let turned = (z, size_x - 1 - x);
+            x = turned.0;
+            z = turned.1;
+
+            std::mem::swap(&mut size_x, &mut size_z);
+        }
+
+        Vec3(x, y, z)
+    }
+
+    /// Rotation of a block once transformed, following its position
+    pub fn rotate(&self, rotation: BlockRotation) -> BlockRotation {
+        use BlockRotation::*;
+
+        let flip = |rot: u32| (360 - rot) % 360;
+
+        let mut rotation = rotation;
+
+        if self.mirror_x {
+            rotation = match rotation {
+                PX(rot) => NX(rot),
+                NX(rot) => PX(rot),
+                PY(rot) => PY(flip(rot)),
+                NY(rot) => NY(flip(rot)),
+                PZ(rot) => PZ(flip(rot)),
+                NZ(rot) => NZ(flip(rot)),
+            };
+        }
+
+        if self.mirror_z {
+            rotation = match rotation {
+                PX(rot) => PX(flip(rot)),
+                NX(rot) => NX(flip(rot)),
+                PY(rot) => PY(flip(rot)),
+                NY(rot) => NY(flip(rot)),
+                PZ(rot) => NZ(rot),
+                NZ(rot) => PZ(rot),
+            };
+        }
+
+        for _ in 0..self.turns % 4 {
+            rotation = match rotation {
+                PX(rot) => NZ(rot),
+                NZ(rot) => NX(rot),
+                NX(rot) => PZ(rot),
+                PZ(rot) => PX(rot),
+                PY(rot) => PY((rot + 90) % 360),
+                // upside down, the block's own turn goes the other way
+                NY(rot) => NY((rot + 270) % 360),
+            };
+        }
+
+        rotation
+    }
+}
+
+/// A cuboid of blocks saved to be pasted elsewhere, possibly into another world
+///
+/// Blocks are stored by name through a palette, so that schematics survive block ids changing.
+/// Each block is a raw voxel value, with the index of its name in the palette instead of its id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schematic {
+    pub size: Vec3<i32>,
+    pub palette: Vec<String>,
+    /// Blocks along y then z then x, as in `Cuboid::voxels`
+    pub blocks: Vec<u32>,
+}
+
+impl Schematic {
+    /// Create a schematic of the voxels of a cuboid, read in the order of `Cuboid::voxels`
+    pub fn from_voxels(cuboid: &Cuboid, voxels: &[VoxelEdit], registry: &Registry) -> Self {
+        let mut palette = vec![];
+        let mut indices = HashMap::new();
+
+        let blocks = voxels
+            .iter()
+            .map(|edit| {
+                let index = *indices.entry(edit.id).or_insert_with(|| {
+                    palette.push(registry.get_block_by_id(edit.id).name.clone());
+                    palette.len() as u32 - 1
+                });
+
+                Blocks::insert_id(edit.to_raw(), index)
+            })
+            .collect();
+
+        Self {
+            size: cuboid.size(),
+            palette,
+            blocks,
+        }
+    }
+
+    /// Edits pasting the schematic transformed, with its minimum corner at `origin`
+    ///
+    /// Fails if the schematic is broken or has blocks this world doesn't know.
+    pub fn to_edits(
+        &self,
+        origin: &Vec3<i32>,
+        transform: &Transform,
+        registry: &Registry,
+    ) -> Result<Vec<VoxelEdit>, String> {
+        let cuboid = Cuboid::new(&Vec3(0, 0, 0), &self.size.sub(&Vec3(1, 1, 1)));
+
+        if self.size.0 <= 0
+            || self.size.1 <= 0
+            || self.size.2 <= 0
+            || cuboid.volume() != self.blocks.len()
+        {
+            return Err("The schematic's size doesn't match its blocks".to_owned());
+        }
+
+        let unknown = self
+            .palette
+            .iter()
+            .filter(|name| !registry.has_name(name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !unknown.is_empty() {
+            return Err(format!("Unknown blocks: {}", unknown.join(", ")));
+        }
+
+        let ids = self
+            .palette
+            .iter()
+            .map(|name| *registry.get_id_by_name(name))
+            .collect::<Vec<_>>();
+
+        cuboid
+            .voxels()
+            .zip(self.blocks.iter())
+            .map(|(position, &raw)| {
+                if (raw >> 16) & 0xF > 5 || (raw >> 20) & 0xF > 7 {
+                    return Err(format!("Unknown block rotation in {:#x}", raw));
+                }
+
+                let voxel = origin.add(&transform.position(&position, &self.size));
+
+                let mut edit = VoxelEdit::from_raw(voxel, raw);
+                edit.id = *ids
+                    .get(edit.id as usize)
+                    .ok_or_else(|| format!("Block {} isn't in the palette", edit.id))?;
+
+                let block = registry.get_block_by_id(edit.id);
+
+                if block.rotatable {
+                    let rotation =
+                        transform.rotate(BlockRotation::encode(edit.rotation, edit.y_rotation));
+                    let (rotation, y_rotation) = BlockRotation::decode(&rotation);
+
+                    edit.rotation = rotation;
+                    edit.y_rotation = if block.y_rotatable { y_rotation } else { 0 };
+                }
+
+                Ok(edit)
+            })
+            .collect()
+    }
+
+    /// Path to the schematic of a name within `folder`, `None` if the name isn't made of
+    /// letters, digits, dashes and underscores
+    pub fn path_of(folder: &Path, name: &str) -> Option<PathBuf> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+
+        Some(folder.join(format!("{}.json", name)))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Write the schematic to disk, to a temporary file first to never leave a broken file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("json.tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+
+        fs::rename(tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_positions_and_rotations() {
+        let size = Vec3(3, 2, 2);
+
+        let turn = Transform::parse(Some(90), None).unwrap();
+        assert_eq!(turn.size(&size), Vec3(2, 2, 3));
+        assert_eq!(turn.position(&Vec3(0, 1, 0), &size), Vec3(0, 1, 2));
+        assert_eq!(turn.position(&Vec3(2, 0, 1), &size), Vec3(1, 0, 0));
+        assert_eq!(turn.rotate(BlockRotation::PX(0)), BlockRotation::NZ(0));
+        assert_eq!(turn.rotate(BlockRotation::PY(315)), BlockRotation::PY(45));
+
+        let mirror = Transform::parse(None, Some("x")).unwrap();
+        assert_eq!(mirror.position(&Vec3(0, 0, 1), &size), Vec3(2, 0, 1));
+        assert_eq!(mirror.rotate(BlockRotation::NX(90)), BlockRotation::PX(90));
+        assert_eq!(mirror.rotate(BlockRotation::PY(90)), BlockRotation::PY(270));
+
+        let full = Transform::parse(Some(-270), Some("xz")).unwrap();
+        assert_eq!(full.turns, 1);
+
+        let cuboid = Cuboid::new(&Vec3(0, 0, 0), &size.sub(&Vec3(1, 1, 1)));
+        let mut turned = cuboid
+            .voxels()
+            .map(|voxel| full.position(&voxel, &size))
+            .collect::<Vec<_>>();
+        turned.sort_by_key(|Vec3(x, y, z)| (*x, *z, *y));
+        assert_eq!(
+            turned,
+            Cuboid::new(&Vec3(0, 0, 0), &Vec3(1, 1, 2))
+                .voxels()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(Transform::parse(Some(45), None).is_err());
+        assert!(Transform::parse(None, Some("y")).is_err());
+    }
+}
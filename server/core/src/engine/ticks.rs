@@ -0,0 +1,538 @@
+use hashbrown::{HashMap, HashSet};
+use rand::Rng;
+use serde::Deserialize;
+
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+
+use server_common::{types::BlockRule, vec::Vec3};
+
+use crate::{constants::VOXEL_NEIGHBORS, gen::lights::LightColor};
+
+use super::{chunks::Chunks, registry::Registry};
+
+/// Highest stage a voxel can have, bound by the 4 bits of the voxel stage
+const MAX_STAGE: u32 = 15;
+
+/// How blocks get ticked, configured per world through `blockTicks` in `worlds.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TickConfig {
+    /// Voxels picked at random per sub-chunk on every round of random ticks
+    #[serde(default = "default_random_ticks")]
+    pub random_ticks: u32,
+
+    /// Ticks between two rounds of random ticks
+    #[serde(default = "default_random_interval")]
+    pub random_interval: i32,
+
+    /// Maximum amount of scheduled ticks processed per tick, the rest wait for the next ticks
+    #[serde(default = "default_max_scheduled")]
+    pub max_scheduled: usize,
+}
+
+fn default_random_ticks() -> u32 {
+    3
+}
+
+fn default_random_interval() -> i32 {
+    20
+}
+
+fn default_max_scheduled() -> usize {
+    256
+}
+
+impl Default for TickConfig {
+    fn default() -> Self {
+        Self {
+            random_ticks: default_random_ticks(),
+            random_interval: default_random_interval(),
+            max_scheduled: default_max_scheduled(),
+        }
+    }
+}
+
+/// What caused a block to tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TickKind {
+    Scheduled,
+    Random,
+}
+
+/// A block tick scheduled for a voxel, only fired if the voxel is still of type `id`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScheduledTick {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+}
+
+/// A change of a voxel's type and stage caused by a block tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockChange {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+    pub stage: u32,
+}
+
+/// How a type of block reacts to being ticked.
+///
+/// Behaviors read the world through `Chunks` and return the changes to apply, they can also
+/// schedule further ticks through `BlockTicks::schedule`.
+pub trait BlockBehavior: Debug + Send + Sync {
+    /// Whether blocks of this type receive random ticks
+    fn random_ticks(&self) -> bool {
+        true
+    }
+
+    /// React to a tick of the block at `voxel`
+    fn tick(
+        &self,
+        chunks: &Chunks,
+        ticks: &mut BlockTicks,
+        voxel: &Vec3<i32>,
+        kind: TickKind,
+    ) -> Vec<BlockChange>;
+}
+
+/// Block behaviors keyed by block id
+#[derive(Debug, Default, Clone)]
+pub struct BlockBehaviors {
+    behaviors: HashMap<u32, Arc<dyn BlockBehavior>>,
+}
+
+impl BlockBehaviors {
+    /// Create the built-in block behaviors of a registry
+    pub fn new(registry: &Registry) -> Self {
+        let mut behaviors = Self::default();
+
+        if registry.has_name("Ice") && registry.has_name("Water") {
+            behaviors.register(
+                registry,
+                "Ice",
+                Arc::new(Melting {
+                    into: *registry.get_id_by_name("Water"),
+                    light_level: 12,
+                }),
+            );
+        }
+
+        for (&id, block) in registry.blocks.iter() {
+            let behavior: Arc<dyn BlockBehavior> = if !block.rules.is_empty() {
+                // blocks turning into others by the rules of their metadata
+                Arc::new(Transforming {
+                    rules: block
+                        .rules
+                        .iter()
+                        .map(|rule| Rule::new(registry, rule))
+                        .collect(),
+                })
+            } else if block.stages.len() > 1 && block.growth_chance > 0.0 {
+                // blocks growing through their stages
+                Arc::new(Growth {
+                    chance: block.growth_chance,
+                    max_stage: registry.get_max_stage(id).min(MAX_STAGE),
+                })
+            } else {
+                continue;
+            };
+
+            behaviors.behaviors.insert(id, behavior);
+        }
+
+        behaviors
+    }
+
+    /// Register the behavior of a block by name, replacing any previous behavior
+    pub fn register(&mut self, registry: &Registry, name: &str, behavior: Arc<dyn BlockBehavior>) {
+        self.behaviors
+            .insert(*registry.get_id_by_name(name), behavior);
+    }
+
+    /// Get the behavior of a block type
+    pub fn get(&self, id: u32) -> Option<&Arc<dyn BlockBehavior>> {
+        self.behaviors.get(&id)
+    }
+
+    /// Whether any block type receives random ticks
+    pub fn has_random_ticks(&self) -> bool {
+        self.behaviors.values().any(|b| b.random_ticks())
+    }
+}
+
+/// Resource of the block ticks scheduled in the future, ordered by the tick they're due
+#[derive(Debug, Default)]
+pub struct BlockTicks {
+    pub config: TickConfig,
+
+    now: i32,
+    scheduled: BTreeMap<i32, Vec<ScheduledTick>>,
+    queued: HashSet<ScheduledTick>,
+}
+
+impl BlockTicks {
+    pub fn new(config: TickConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Amount of block ticks scheduled
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Whether no block tick is scheduled
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Schedule the block `id` at `voxel` to be ticked in `delay` ticks
+    pub fn schedule(&mut self, voxel: Vec3<i32>, id: u32, delay: i32) {
+        let tick = ScheduledTick { voxel, id };
+
+        if self.queued.insert(tick.clone()) {
+            self.scheduled
+                .entry(self.now + delay.max(1))
+                .or_default()
+                .push(tick);
+        }
+    }
+
+    /// Advance to `tick`, taking the block ticks due by then, at most `max_scheduled` of them
+    pub fn due(&mut self, tick: i32) -> Vec<ScheduledTick> {
+        self.now = tick;
+
+        let mut due = vec![];
+
+        while due.len() < self.config.max_scheduled {
+            let mut entry = match self.scheduled.first_entry() {
+                Some(entry) if *entry.key() <= tick => entry,
+                _ => break,
+            };
+
+            let ticks = entry.get_mut();
+            let count = ticks.len().min(self.config.max_scheduled - due.len());
+
+            due.extend(ticks.drain(..count));
+
+            if ticks.is_empty() {
+                entry.remove();
+            }
+        }
+
+        due.iter().for_each(|tick| {
+            self.queued.remove(tick);
+        });
+
+        due
+    }
+
+    /// Whether a round of random ticks happens at `tick`
+    pub fn is_random_tick(&self, tick: i32) -> bool {
+        self.config.random_ticks > 0
+            && self.config.random_interval > 0
+            && tick % self.config.random_interval == 0
+    }
+
+    /// Pick `random_ticks` voxels at random within each sub-chunk of every chunk ready for changes
+    pub fn random_voxels(&self, chunks: &Chunks) -> Vec<Vec3<i32>> {
+        let mut rng = rand::thread_rng();
+        let mut voxels = vec![];
+
+        let sub_chunks = chunks.config.sub_chunks as i32;
+        let unit = chunks.config.max_height as i32 / sub_chunks;
+
+        for chunk in chunks.all() {
+            if chunk.needs_terrain || chunk.needs_decoration || chunk.needs_propagation {
+                continue;
+            }
+
+            // the padding belongs to the neighbors, which tick it themselves
+            let Vec3(min_x, _, min_z) = chunk.min_inner;
+            let Vec3(max_x, _, max_z) = chunk.max_inner;
+
+            for level in 0..sub_chunks {
+                for _ in 0..self.config.random_ticks {
+                    voxels.push(Vec3(
+                        rng.gen_range(min_x..max_x),
+                        rng.gen_range(level * unit..(level + 1) * unit),
+                        rng.gen_range(min_z..max_z),
+                    ));
+                }
+            }
+        }
+
+        voxels
+    }
+}
+
+/// Turns into another block when a torch light of `light_level` or more shines next to it
+#[derive(Debug)]
+pub struct Melting {
+    pub into: u32,
+    pub light_level: u32,
+}
+
+impl BlockBehavior for Melting {
+    fn tick(
+        &self,
+        chunks: &Chunks,
+        _ticks: &mut BlockTicks,
+        voxel: &Vec3<i32>,
+        _kind: TickKind,
+    ) -> Vec<BlockChange> {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        let lit = VOXEL_NEIGHBORS.iter().any(|[ox, oy, oz]| {
+            let (nx, ny, nz) = (vx + ox, vy + oy, vz + oz);
+
+            if ny < 0 || ny >= chunks.config.max_height as i32 {
+                return false;
+            }
+
+            [LightColor::Red, LightColor::Green, LightColor::Blue]
+                .iter()
+                .any(|color| chunks.get_torch_light(nx, ny, nz, color) >= self.light_level)
+        });
+
+        if !lit {
+            return vec![];
+        }
+
+        vec![BlockChange {
+            voxel: voxel.clone(),
+            id: self.into,
+            stage: 0,
+        }]
+    }
+}
+
+/// Grows to the next stage with a chance on every random tick, until the last stage
+#[derive(Debug)]
+pub struct Growth {
+    pub chance: f32,
+    pub max_stage: u32,
+}
+
+impl BlockBehavior for Growth {
+    fn tick(
+        &self,
+        chunks: &Chunks,
+        _ticks: &mut BlockTicks,
+        voxel: &Vec3<i32>,
+        _kind: TickKind,
+    ) -> Vec<BlockChange> {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        // scheduled ticks can outlive the chunk they were scheduled in
+        if chunks.get_chunk_by_voxel(vx, vy, vz).is_none() {
+            return vec![];
+        }
+
+        let stage = chunks.get_voxel_stage_by_voxel(vx, vy, vz);
+
+        match self.grow(stage, rand::thread_rng().gen()) {
+            Some(stage) => vec![BlockChange {
+                voxel: voxel.clone(),
+                id: chunks.get_voxel_by_voxel(vx, vy, vz),
+                stage,
+            }],
+            None => vec![],
+        }
+    }
+}
+
+impl Growth {
+    /// The stage a voxel at `stage` grows to for a `roll` from 0 to 1, if it grows at all
+    pub fn grow(&self, stage: u32, roll: f32) -> Option<u32> {
+        if stage >= self.max_stage || roll >= self.chance {
+            return None;
+        }
+
+        Some(stage + 1)
+    }
+}
+
+/// A `BlockRule` with its block names resolved to ids
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub into: u32,
+    pub chance: f32,
+    pub near: Vec<u32>,
+    pub min_sunlight: Option<u32>,
+    pub max_sunlight: Option<u32>,
+    pub covered: Option<bool>,
+}
+
+impl Rule {
+    pub fn new(registry: &Registry, rule: &BlockRule) -> Self {
+        Self {
+            into: *registry.get_id_by_name(&rule.into),
+            chance: rule.chance,
+            near: rule
+                .near
+                .iter()
+                .map(|name| *registry.get_id_by_name(name))
+                .collect(),
+            min_sunlight: rule.min_sunlight,
+            max_sunlight: rule.max_sunlight,
+            covered: rule.covered,
+        }
+    }
+
+    /// Check whether the conditions of the rule are met at `voxel`
+    pub fn matches(&self, chunks: &Chunks, voxel: &Vec3<i32>) -> bool {
+        let &Vec3(vx, vy, vz) = voxel;
+        let max_height = chunks.config.max_height as i32;
+
+        if self.min_sunlight.is_some() || self.max_sunlight.is_some() {
+            let sunlight = if vy + 1 >= max_height {
+                chunks.config.max_light_level
+            } else {
+                chunks.get_sunlight(vx, vy + 1, vz)
+            };
+
+            if self.min_sunlight.is_some_and(|min| sunlight < min)
+                || self.max_sunlight.is_some_and(|max| sunlight > max)
+            {
+                return false;
+            }
+        }
+
+        if let Some(covered) = self.covered {
+            let is_covered = vy + 1 < max_height && {
+                let above =
+                    chunks
+                        .registry
+                        .get_block_by_id(chunks.get_voxel_by_voxel(vx, vy + 1, vz));
+                above.is_solid && !above.is_transparent
+            };
+
+            if is_covered != covered {
+                return false;
+            }
+        }
+
+        if !self.near.is_empty() {
+            let mut found = false;
+
+            'search: for ox in -1..=1 {
+                for oy in -1..=1 {
+                    for oz in -1..=1 {
+                        let ny = vy + oy;
+
+                        if (ox == 0 && oy == 0 && oz == 0) || ny < 0 || ny >= max_height {
+                            continue;
+                        }
+
+                        if self
+                            .near
+                            .contains(&chunks.get_voxel_by_voxel(vx + ox, ny, vz + oz))
+                        {
+                            found = true;
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            if !found {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Turns into another block by the first of its rules met on a random tick
+#[derive(Debug)]
+pub struct Transforming {
+    pub rules: Vec<Rule>,
+}
+
+impl BlockBehavior for Transforming {
+    fn tick(
+        &self,
+        chunks: &Chunks,
+        _ticks: &mut BlockTicks,
+        voxel: &Vec3<i32>,
+        _kind: TickKind,
+    ) -> Vec<BlockChange> {
+        let mut rng = rand::thread_rng();
+
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(chunks, voxel))
+            .filter(|rule| rng.gen::<f32>() < rule.chance)
+            .map(|rule| {
+                vec![BlockChange {
+                    voxel: voxel.clone(),
+                    id: rule.into,
+                    stage: 0,
+                }]
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_and_take_due() {
+        let mut ticks = BlockTicks::new(TickConfig {
+            max_scheduled: 2,
+            ..Default::default()
+        });
+
+        ticks.due(10);
+        ticks.schedule(Vec3(0, 0, 0), 1, 5);
+        ticks.schedule(Vec3(0, 0, 0), 1, 2);
+        ticks.schedule(Vec3(0, 0, 0), 2, 2);
+        ticks.schedule(Vec3(1, 0, 0), 1, 0);
+
+        assert_eq!(ticks.len(), 3);
+
+        let first = ticks.due(12);
+        assert_eq!(
+            first,
+            vec![
+                ScheduledTick {
+                    voxel: Vec3(1, 0, 0),
+                    id: 1
+                },
+                ScheduledTick {
+                    voxel: Vec3(0, 0, 0),
+                    id: 2
+                },
+            ]
+        );
+
+        assert!(ticks.due(14).is_empty());
+        assert_eq!(ticks.due(15).len(), 1);
+        assert!(ticks.is_empty());
+    }
+
+    #[test]
+    fn crops_grow_through_their_stages() {
+        let registry = Registry::from_blocks(vec![(103, Registry::load_block("wheat.json"))]);
+        let behaviors = BlockBehaviors::new(&registry);
+
+        assert!(behaviors.get(103).is_some());
+        assert!(behaviors.has_random_ticks());
+
+        let growth = Growth {
+            chance: 0.2,
+            max_stage: registry.get_max_stage(103),
+        };
+
+        assert_eq!(growth.max_stage, 2);
+        assert_eq!(growth.grow(0, 0.1), Some(1));
+        assert_eq!(growth.grow(0, 0.5), None);
+        assert_eq!(growth.grow(1, 0.0), Some(2));
+        assert_eq!(growth.grow(2, 0.0), None);
+    }
+}
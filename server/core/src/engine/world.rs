@@ -1,21 +1,30 @@
 #![allow(dead_code)]
 
 use actix::Recipient;
-use log::info;
+use log::{debug, error, info};
 
 use ansi_term::Colour::Yellow;
 
 use specs::shred::{Fetch, FetchMut, Resource};
 
-use std::io::Write;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Instant;
-use std::{collections::VecDeque, fs::File};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::File,
+};
 
-use specs::{Builder, DispatcherBuilder, World as ECSWorld, WorldExt};
+use specs::{Builder, DispatcherBuilder, Join, World as ECSWorld, WorldExt};
 
 use serde::{Deserialize, Serialize};
 
 use server_common::quaternion::Quaternion;
+use server_utils::{
+    convert::{map_voxel_to_chunk, map_world_to_voxel},
+    raycast,
+};
 
 use crate::comp::brain::Brain;
 use crate::comp::curr_chunk::CurrChunk;
@@ -26,10 +35,12 @@ use crate::comp::rotation::Rotation;
 use crate::comp::target::Target;
 use crate::comp::view_radius::ViewRadius;
 use crate::comp::walk_towards::WalkTowards;
+use crate::gen::terrain::{SuperflatConfig, TerrainGenerator};
 use crate::network::models::{create_of_type, ChatType};
 use crate::sys::{
-    BroadcastSystem, ChunkingSystem, EntitiesSystem, GenerationSystem, MeshingSystem,
-    ObserveSystem, PathFindSystem, PeersSystem, SearchSystem, WalkTowardsSystem,
+    BlockTicksSystem, BroadcastSystem, ChunkingSystem, EntitiesSystem, FluidsSystem,
+    GenerationSystem, MeshingSystem, ObserveSystem, PathFindSystem, PeersSystem, SearchSystem,
+    UnloadSystem, WalkTowardsSystem,
 };
 use crate::{
     comp::rigidbody::RigidBody,
@@ -40,11 +51,12 @@ use super::entities::Entities;
 use super::kdtree::KdTree;
 use super::{
     super::{
-        constants::WORLD_DATA_FILE,
-        engine::chunks::MeshLevel,
+        constants::{
+            CHUNK_NEIGHBORS, JOURNAL_FILE, LEVEL_SEED, PERMISSIONS_FILE, VOXEL_NEIGHBORS,
+            WORLD_DATA_FILE,
+        },
         network::models::{
-            create_chat_message, create_message, messages, ChunkProtocol, MessageComponents,
-            MessageType,
+            create_chat_message, create_message, messages, MessageComponents, MessageType,
         },
         sys::PhysicsSystem,
     },
@@ -57,16 +69,50 @@ use server_common::{
     vec::{Vec2, Vec3},
 };
 
-use super::chunks::Chunks;
+use super::backup::{BackupConfig, Backups};
+use super::chunks::{ChunkLoadPolicy, Chunks};
 use super::clock::Clock;
+use super::commands::{Command, CommandSender, Commands};
+use super::edits::{Cuboid, EditBatch, EditConfig, EditLimiter, EditRejection, VoxelEdit};
+use super::fluids::{FluidConfig, Fluids};
+use super::history::History;
+use super::journal::Journal;
+use super::permissions::{PermissionConfig, Permissions, Role};
 use super::players::{BroadcastExt, PlayerUpdates, Players};
 use super::registry::Registry;
+use super::ticks::{BlockBehaviors, BlockTicks, TickConfig};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WorldData {
     time: f32,
     tick_speed: f32,
+
+    /// Seed the world was generated with, missing from worlds saved before seeds were configurable
+    #[serde(default)]
+    seed: Option<u32>,
+}
+
+impl WorldData {
+    /// Read the data file of a saved world, `None` if the world was never saved.
+    fn load(config: &WorldConfig, world_name: &str) -> Result<Option<Self>, String> {
+        let mut path = PathBuf::from(&config.chunk_root);
+        path.push(world_name);
+        path.push(WORLD_DATA_FILE);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+
+        serde_json::from_reader(file)
+            .map(Some)
+            .map_err(|e| format!("unable to read {:?}: {}", path, e))
+    }
+}
+
+fn default_seed() -> u32 {
+    LEVEL_SEED
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -86,6 +132,35 @@ pub struct WorldConfig {
     pub player_head: f32,
     pub max_per_thread: usize,
     pub server_tick_rate: u64,
+
+    /// Seed of all the noises used to generate the world's terrain
+    #[serde(default = "default_seed")]
+    pub seed: u32,
+
+    /// Names of the structures of `structures.json` placed while decorating chunks, none if empty
+    #[serde(default)]
+    pub structures: Vec<String>,
+
+    #[serde(default)]
+    pub superflat: SuperflatConfig,
+
+    #[serde(default)]
+    pub fluids: FluidConfig,
+
+    #[serde(default)]
+    pub block_ticks: TickConfig,
+
+    #[serde(default)]
+    pub chunk_load_policy: ChunkLoadPolicy,
+
+    #[serde(default)]
+    pub backups: BackupConfig,
+
+    #[serde(default)]
+    pub edits: EditConfig,
+
+    #[serde(default)]
+    pub permissions: PermissionConfig,
 }
 
 #[derive(Deserialize, Clone)]
@@ -97,6 +172,9 @@ pub struct WorldMeta {
     pub tick_speed: f32,
     pub time: f32,
 
+    #[serde(default = "default_seed")]
+    pub seed: u32,
+
     #[serde(default)]
     pub packs: Vec<String>,
 }
@@ -120,8 +198,39 @@ pub type MessagesQueue = Vec<(
 impl World {
     /// Instantiate a new voxel world, registers the necessary components and resources
     ///
-    /// Attempts to save the world data to its corresponding JSON file.
-    pub fn new(meta: WorldMeta, config: WorldConfig, registry: Registry) -> Self {
+    /// Attempts to save the world data to its corresponding JSON file. Worlds that were already
+    /// saved keep generating with the seed they were saved with, regardless of their config.
+    pub fn new(
+        mut meta: WorldMeta,
+        mut config: WorldConfig,
+        registry: Registry,
+        generator: Arc<dyn TerrainGenerator>,
+    ) -> Self {
+        if config.save {
+            match WorldData::load(&config, &meta.name) {
+                Ok(Some(data)) => {
+                    // worlds saved before seeds were configurable all used `LEVEL_SEED`
+                    let seed = data.seed.unwrap_or(LEVEL_SEED);
+
+                    if seed != config.seed {
+                        info!(
+                            "World \"{}\" was generated with seed {}, ignoring configured seed {}.",
+                            meta.name, seed, config.seed
+                        );
+                    }
+
+                    config.seed = seed;
+                }
+                Ok(None) => {}
+                Err(e) => error!(
+                    "World data of \"{}\" is broken, generating with configured seed {}: {}",
+                    meta.name, config.seed, e
+                ),
+            }
+        }
+
+        meta.seed = config.seed;
+
         let WorldMeta {
             name,
             description,
@@ -147,8 +256,32 @@ impl World {
 
         // ECS Resources
         ecs.insert(name.to_owned());
-        ecs.insert(Chunks::new(&name, config.clone(), registry));
+        ecs.insert(BlockBehaviors::new(&registry));
+        ecs.insert(Chunks::new(&name, config.clone(), registry, generator));
         ecs.insert(Clock::new(time, tick_speed));
+        ecs.insert(Fluids::new(config.fluids.clone()));
+        ecs.insert(BlockTicks::new(config.block_ticks.clone()));
+        ecs.insert(Permissions::load(
+            config.permissions.clone(),
+            if config.save {
+                let mut path = PathBuf::from(&config.chunk_root);
+                path.push(&name);
+                path.push(PERMISSIONS_FILE);
+                Some(path)
+            } else {
+                None
+            },
+        ));
+        ecs.insert(Commands::new());
+        ecs.insert(History::new(config.edits.history_size));
+        ecs.insert(Journal::new(if config.save {
+            let mut path = PathBuf::from(&config.chunk_root);
+            path.push(&name);
+            path.push(JOURNAL_FILE);
+            Some(path)
+        } else {
+            None
+        }));
         ecs.insert(KdTree::new());
         ecs.insert(Players::new());
         ecs.insert(PlayerUpdates::new());
@@ -288,6 +421,7 @@ impl World {
             name: player_name,
             addr: player_addr,
             requested_chunks: VecDeque::default(),
+            edits: EditLimiter::default(),
         };
 
         players.insert(id, new_player);
@@ -383,6 +517,11 @@ impl World {
 
     /// Handles server-side config change
     pub fn on_config(&mut self, player_id: usize, msg: messages::Message) {
+        if self.role_of(player_id) < Role::Operator {
+            self.reject_config(player_id);
+            return;
+        }
+
         let mut clock = self.write_resource::<Clock>();
 
         let json = msg.parse_json().unwrap();
@@ -407,17 +546,76 @@ impl World {
         self.broadcast_lazy(&new_message, vec![], vec![], player_id);
     }
 
+    /// Answer a config change a player isn't allowed to make with the actual config
+    fn reject_config(&mut self, player_id: usize) {
+        let clock = self.read_resource::<Clock>();
+
+        let mut new_message = create_of_type(MessageType::Config);
+        new_message.json = serde_json::json!({
+            "time": clock.time,
+            "tickSpeed": clock.tick_speed,
+        })
+        .to_string();
+
+        drop(clock);
+
+        let error = create_chat_message(
+            MessageType::Message,
+            ChatType::Error,
+            "",
+            "You don't have permission to change the world's settings.",
+        );
+
+        self.broadcast(&new_message, vec![player_id], vec![]);
+        self.broadcast(&error, vec![player_id], vec![]);
+    }
+
+    /// Role of a connected player in this world
+    pub fn role_of(&self, player_id: usize) -> Role {
+        let players = self.read_resource::<Players>();
+        let name = players.get(&player_id).and_then(|p| p.name.as_deref());
+
+        self.read_resource::<Permissions>().role_of(name)
+    }
+
     /// Handles server-side voxel updates
     ///
     /// Remesh chunks based on which sub-chunks are changed according to internal
-    /// chunk caching system.
-    pub fn on_update(&mut self, _player_id: usize, msg: messages::Message) {
+    /// chunk caching system. Updates failing validation are answered by the voxels' actual
+    /// state to the player who sent them.
+    pub fn on_update(&mut self, player_id: usize, msg: messages::Message) {
+        let mut updates = vec![];
+        let mut rejected = vec![];
+
+        for update in msg.updates {
+            match self.validate_update(player_id, &update) {
+                Ok(()) => updates.push(update),
+                Err(rejection) => {
+                    debug!(
+                        "Rejected update from {} at {:?}: {:?}",
+                        player_id,
+                        (update.vx, update.vy, update.vz),
+                        rejection
+                    );
+                    rejected.push(update);
+                }
+            }
+        }
+
+        if !rejected.is_empty() {
+            self.correct_updates(player_id, rejected);
+        }
+
+        if updates.is_empty() {
+            return;
+        }
+
         let mut chunks = self.write_resource::<Chunks>();
 
         let &air = chunks.registry.get_id_by_name("Air");
 
-        let mut updates = msg.updates;
         let mut results = vec![];
+        let mut batch = EditBatch::default();
 
         while !updates.is_empty() {
             let update = updates.pop().unwrap();
@@ -443,10 +641,14 @@ impl World {
                 continue;
             }
 
+            let before = chunks.get_voxel_edit(vx, vy, vz);
+
             chunks.start_caching();
             chunks.update(vx, vy, vz, id, rotation, y_rotation);
             chunks.stop_caching();
 
+            batch.push(before, chunks.get_voxel_edit(vx, vy, vz));
+
             let neighbor_chunks = chunks.get_neighbor_chunk_coords(vx, vy, vz);
             neighbor_chunks.into_iter().for_each(|c| {
                 chunks.chunk_cache.insert(c);
@@ -469,46 +671,196 @@ impl World {
             results.push(update);
         }
 
-        let cache = chunks.chunk_cache.clone();
-        chunks.clear_cache();
+        let chunk_protocols = chunks.drain_cache();
 
         drop(chunks);
 
-        let mut chunk_mesh_protocols = vec![];
+        let tick = self.read_resource::<Clock>().tick;
+        let mut fluids = self.write_resource::<Fluids>();
 
-        cache.iter().for_each(|coords| {
-            let mut chunks = self.write_resource::<Chunks>();
-
-            let levels = chunks.raw(&coords).unwrap().dirty_levels.clone();
-            let mesh_level = MeshLevel::Levels(levels);
-
-            let chunk = chunks.get(&coords, &mesh_level, true).unwrap();
-            chunk_mesh_protocols.push(chunk.get_protocol(true, false, false, mesh_level));
-
-            drop(chunks);
+        results.iter().for_each(|update| {
+            fluids.schedule_around(&Vec3(update.vx, update.vy, update.vz), tick);
         });
 
-        let chunks = self.read_resource::<Chunks>();
+        drop(fluids);
 
-        // First send the message, so borrow checker doesn't freak out
         let mut components = MessageComponents::default_for(MessageType::Update);
-        let mut chunk_protocols: Vec<ChunkProtocol> = cache
-            .iter()
-            .map(|coords| {
-                chunks
-                    .get_chunk(coords)
-                    .unwrap()
-                    .get_protocol(false, false, true, MeshLevel::None)
-            })
-            .collect();
-        chunk_protocols.append(&mut chunk_mesh_protocols);
         components.chunks = Some(chunk_protocols);
         let mut new_message = create_message(components);
         new_message.updates = results;
 
+        self.broadcast(&new_message, vec![], vec![]);
+
+        let name = self
+            .read_resource::<Players>()
+            .get(&player_id)
+            .and_then(|player| player.name.clone());
+
+        // logged already, the edits went through either way
+        self.journal_edits(player_id, name.as_deref().unwrap_or_default(), &batch)
+            .ok();
+
+        if let Some(name) = name {
+            self.write_resource::<History>().record(&name, batch);
+        }
+    }
+
+    /// Append the changes of a batch of edits made by a player to the world's journal, logging
+    /// the error if they couldn't be, in which case `/rollback` won't see them
+    pub fn journal_edits(
+        &mut self,
+        player_id: usize,
+        player: &str,
+        batch: &EditBatch,
+    ) -> io::Result<()> {
+        let result = self
+            .write_resource::<Journal>()
+            .record(player_id, player, batch);
+
+        if let Err(e) = &result {
+            error!("Unable to journal the block changes of {}: {}", player, e);
+        }
+
+        result
+    }
+
+    /// Check whether a player is allowed to make a voxel update
+    fn validate_update(
+        &self,
+        player_id: usize,
+        update: &messages::Update,
+    ) -> Result<(), EditRejection> {
+        let chunks = self.read_resource::<Chunks>();
+        let config = self.read_resource::<WorldConfig>().edits.clone();
+
+        let &messages::Update { vx, vy, vz, .. } = update;
+        let id = update.r#type;
+
+        if vy < 0 || vy >= chunks.config.max_height as i32 || !chunks.registry.has_type(id) {
+            return Err(EditRejection::Invalid);
+        }
+
+        if chunks
+            .get_chunk_by_voxel(vx, vy, vz)
+            .is_none_or(|chunk| chunk.needs_propagation)
+        {
+            return Err(EditRejection::Invalid);
+        }
+
+        let mut players = self.ecs.write_resource::<Players>();
+        let player = players.get_mut(&player_id).ok_or(EditRejection::Invalid)?;
+
+        if !self
+            .read_resource::<Permissions>()
+            .allows(player.name.as_deref(), Role::Builder)
+        {
+            return Err(EditRejection::Forbidden);
+        }
+
+        if !player
+            .edits
+            .allow(Instant::now(), config.max_edits_per_second)
+        {
+            return Err(EditRejection::RateLimited);
+        }
+
+        let bodies = self.ecs.read_storage::<RigidBody>();
+        let body = bodies.get(player.entity).ok_or(EditRejection::Invalid)?;
+
+        drop(players);
+
+        let dimension = chunks.config.dimension as f32;
+
+        // measure in voxels, from the player's head to the center of the voxel
+        let mut head = body.get_head_position().scale(1.0 / dimension);
+        let center = Vec3(vx as f32 + 0.5, vy as f32 + 0.5, vz as f32 + 0.5);
+        let mut dir = center.sub(&head);
+        let dist = dir.len();
+
+        if dist > config.reach {
+            return Err(EditRejection::OutOfReach);
+        }
+
+        // stop a voxel short of the target, as rays to voxels placed against others can graze them
+        if config.line_of_sight && dist > 1.0 {
+            let test_solid = |x: i32, y: i32, z: i32| -> bool {
+                (x, y, z) != (vx, vy, vz) && !chunks.get_walkable_by_voxel(x, y, z)
+            };
+
+            if raycast::trace(
+                dist - 1.0,
+                &test_solid,
+                &mut head,
+                &mut dir,
+                &mut Vec3::default(),
+                &mut Vec3::default(),
+            ) {
+                return Err(EditRejection::OutOfSight);
+            }
+        }
+
+        if chunks.registry.get_block_by_id(id).is_solid {
+            // shrunk a bit so that standing right next to the voxel doesn't count
+            let margin = 0.01 * dimension;
+            let voxel_aabb = Aabb::new(
+                &Vec3(
+                    vx as f32 * dimension + margin,
+                    vy as f32 * dimension + margin,
+                    vz as f32 * dimension + margin,
+                ),
+                &Vec3(
+                    dimension - margin * 2.0,
+                    dimension - margin * 2.0,
+                    dimension - margin * 2.0,
+                ),
+            );
+
+            if (&bodies).join().any(|b| b.aabb.intersects(&voxel_aabb)) {
+                return Err(EditRejection::InsideEntity);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the actual state of rejected voxel updates back to the player who made them
+    fn correct_updates(&mut self, player_id: usize, rejected: Vec<messages::Update>) {
+        let chunks = self.read_resource::<Chunks>();
+        let max_height = chunks.config.max_height as i32;
+
+        let corrections = rejected
+            .into_iter()
+            .filter(|update| {
+                update.vy >= 0
+                    && update.vy < max_height
+                    && chunks
+                        .get_chunk_by_voxel(update.vx, update.vy, update.vz)
+                        .is_some()
+            })
+            .map(|messages::Update { vx, vy, vz, .. }| {
+                let edit = chunks.get_voxel_edit(vx, vy, vz);
+
+                messages::Update {
+                    vx,
+                    vy,
+                    vz,
+                    r#type: edit.id,
+                    rotation: edit.rotation,
+                    y_rotation: edit.y_rotation,
+                }
+            })
+            .collect::<Vec<_>>();
+
         drop(chunks);
 
-        self.broadcast(&new_message, vec![], vec![]);
+        if corrections.is_empty() {
+            return;
+        }
+
+        let mut new_message = create_message(MessageComponents::default_for(MessageType::Update));
+        new_message.updates = corrections;
+
+        self.broadcast(&new_message, vec![player_id], vec![]);
     }
 
     /// Adds the player update to the resource `PlayerUpdate`, handled later in an ECS system.
@@ -525,44 +877,305 @@ impl World {
 
             info!("{}: {}", sender, body);
 
-            if body.starts_with('/') {
-                let body = body
-                    .strip_prefix('/')
-                    .unwrap()
-                    .split_whitespace()
-                    .collect::<Vec<_>>();
-
-                let mut msgs = vec![];
-
-                let create_msg = |chat_type: ChatType, body: &str| {
-                    create_chat_message(MessageType::Message, chat_type, "", body)
+            if let Some(line) = body.strip_prefix('/') {
+                let (chat_type, reply) = match self.run_command(player_id, line) {
+                    Ok(reply) => (ChatType::Info, reply),
+                    Err(error) => (ChatType::Error, error),
                 };
 
-                if body.is_empty() {
-                    msgs.push(create_msg(ChatType::Error, "Unknown command."));
-                } else {
-                    match body[0] {
-                        "save" => {
-                            self.save();
-                            msgs.push(create_msg(ChatType::Info, "World has been saved."));
-                        }
-                        "summon" => {
-                            self.test_entity(player_id);
-                            msgs.push(create_msg(ChatType::Info, "Summoned a test entity."));
-                        }
-                        _ => {}
-                    }
+                if !reply.is_empty() {
+                    let new_message =
+                        create_chat_message(MessageType::Message, chat_type, "", &reply);
+                    self.broadcast_lazy(&new_message, vec![player_id], vec![], player_id);
                 }
-
-                msgs.into_iter().for_each(|msg| {
-                    self.broadcast_lazy(&msg, vec![], vec![], player_id);
-                });
             } else {
                 self.broadcast_lazy(&msg, vec![], vec![], player_id);
             }
         }
     }
 
+    /// Register a chat command, replacing any previous command of the same name
+    pub fn register_command(&mut self, command: Command) {
+        self.write_resource::<Commands>().register(command);
+    }
+
+    /// Parse and run a chat command typed by a player, without its leading slash
+    ///
+    /// The command runs on behalf of the name the player joined with, whatever name their
+    /// client puts on its chat messages.
+    pub fn run_command(&mut self, player_id: usize, line: &str) -> Result<String, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        let name = match words.first() {
+            Some(name) => name.to_lowercase(),
+            None => return Err("Unknown command, try /help.".to_owned()),
+        };
+
+        let command = self
+            .read_resource::<Commands>()
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown command \"{}\", try /help.", name))?;
+
+        let sender = self
+            .read_resource::<Players>()
+            .get(&player_id)
+            .and_then(|player| player.name.clone())
+            .ok_or_else(|| "Join the world before running commands.".to_owned())?;
+
+        let role = self.role_of(player_id);
+
+        if role < command.role {
+            return Err(format!(
+                "Only a {} or above can run /{}.",
+                command.role.name(),
+                command.name
+            ));
+        }
+
+        let position = {
+            let players = self.read_resource::<Players>();
+            let bodies = self.ecs.read_storage::<RigidBody>();
+            let dimension = self.read_resource::<WorldConfig>().dimension;
+
+            players
+                .get(&player_id)
+                .and_then(|player| bodies.get(player.entity))
+                .map(|body| {
+                    let Vec3(px, py, pz) = body.get_position();
+                    map_world_to_voxel(px, py, pz, dimension)
+                })
+                .unwrap_or_default()
+        };
+
+        let args = {
+            let chunks = self.read_resource::<Chunks>();
+            let players = self.read_resource::<Players>();
+
+            command
+                .parse(&words[1..], &position, &chunks.registry, &players)
+                .map_err(|error| format!("{}. Usage: {}", error, command.usage()))?
+        };
+
+        let sender = CommandSender {
+            id: player_id,
+            name: sender,
+            role,
+            position,
+        };
+
+        (command.handler)(self, &sender, &args)
+    }
+
+    /// Read the voxels of a cuboid as edits that would restore them, the cuboid having to be
+    /// within loaded chunks
+    pub fn read_voxels(&self, cuboid: &Cuboid) -> Result<Vec<VoxelEdit>, String> {
+        let chunks = self.read_resource::<Chunks>();
+
+        if cuboid
+            .chunks(chunks.config.chunk_size)
+            .iter()
+            .any(|coords| chunks.get_chunk(coords).is_none_or(|c| c.needs_propagation))
+        {
+            return Err("Part of the area isn't loaded yet".to_owned());
+        }
+
+        Ok(cuboid
+            .voxels()
+            .map(|Vec3(vx, vy, vz)| chunks.get_voxel_edit(vx, vy, vz))
+            .collect())
+    }
+
+    /// Apply a batch of voxel edits at once, such as the ones of world-edit commands
+    ///
+    /// Every chunk touched is remeshed once through its dirty levels, and all the changes are
+    /// broadcasted in a single `Update` message. Either all edits are applied or none, when
+    /// some are out of the world or in chunks that aren't ready.
+    ///
+    /// Returns the changes of the voxels that actually changed, in the order applied.
+    pub fn apply_edits(&mut self, edits: Vec<VoxelEdit>) -> Result<EditBatch, String> {
+        let mut chunks = self.write_resource::<Chunks>();
+
+        let max_height = chunks.config.max_height as i32;
+        let chunk_size = chunks.config.chunk_size;
+
+        let mut affected = HashSet::new();
+
+        for VoxelEdit { voxel, .. } in edits.iter() {
+            if voxel.1 < 0 || voxel.1 >= max_height {
+                return Err(format!("{} is out of the world's height", voxel.1));
+            }
+
+            affected.insert(map_voxel_to_chunk(voxel.0, voxel.1, voxel.2, chunk_size));
+        }
+
+        if affected
+            .iter()
+            .any(|coords| chunks.get_chunk(coords).is_none_or(|c| c.needs_propagation))
+        {
+            return Err("Part of the area isn't loaded yet".to_owned());
+        }
+
+        let mut batch = EditBatch::default();
+
+        chunks.start_caching();
+
+        for edit in edits {
+            let Vec3(vx, vy, vz) = edit.voxel;
+            let old = chunks.get_voxel_edit(vx, vy, vz);
+
+            if old == edit {
+                continue;
+            }
+
+            if old.id != edit.id
+                || old.rotation != edit.rotation
+                || old.y_rotation != edit.y_rotation
+            {
+                chunks.update(vx, vy, vz, edit.id, edit.rotation, edit.y_rotation);
+            }
+
+            if chunks.get_voxel_stage_by_voxel(vx, vy, vz) != edit.stage {
+                chunks.set_voxel_stage_by_voxel(vx, vy, vz, edit.stage);
+            }
+
+            batch.push(old, edit);
+        }
+
+        chunks.stop_caching();
+
+        if batch.is_empty() {
+            return Ok(batch);
+        }
+
+        // the padding of neighboring chunks, once for the whole batch
+        for Vec2(cx, cz) in affected.iter() {
+            for [ox, oz] in CHUNK_NEIGHBORS.iter() {
+                chunks.chunk_cache.insert(Vec2(cx + ox, cz + oz));
+            }
+        }
+
+        let chunk_protocols = chunks.drain_cache();
+
+        // only wake fluids up where fluids are involved
+        let near_fluids = batch
+            .after
+            .iter()
+            .zip(batch.before.iter())
+            .filter(|(edit, old)| {
+                let Vec3(vx, vy, vz) = edit.voxel;
+
+                chunks.registry.is_fluid(edit.id)
+                    || chunks.registry.is_fluid(old.id)
+                    || VOXEL_NEIGHBORS.iter().any(|[ox, oy, oz]| {
+                        let ny = vy + oy;
+                        ny >= 0
+                            && ny < max_height
+                            && chunks.registry.is_fluid(chunks.get_voxel_by_voxel(
+                                vx + ox,
+                                ny,
+                                vz + oz,
+                            ))
+                    })
+            })
+            .map(|(edit, _)| edit.voxel.clone())
+            .collect::<Vec<_>>();
+
+        drop(chunks);
+
+        let tick = self.read_resource::<Clock>().tick;
+        let mut fluids = self.write_resource::<Fluids>();
+
+        near_fluids.iter().for_each(|voxel| {
+            fluids.schedule_around(voxel, tick);
+        });
+
+        drop(fluids);
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.chunks = Some(chunk_protocols);
+        let mut new_message = create_message(components);
+        new_message.updates = batch
+            .after
+            .iter()
+            .map(|edit| messages::Update {
+                vx: edit.voxel.0,
+                vy: edit.voxel.1,
+                vz: edit.voxel.2,
+                r#type: edit.id,
+                rotation: edit.rotation,
+                y_rotation: edit.y_rotation,
+            })
+            .collect();
+
+        self.broadcast(&new_message, vec![], vec![]);
+
+        Ok(batch)
+    }
+
+    /// Move a player so that they stand in `voxel`, loading the chunks around the destination
+    ///
+    /// The player is sent a `Teleport` message with the voxel under their feet, for their
+    /// client to snap to.
+    pub fn teleport(&mut self, player_id: usize, voxel: &Vec3<i32>) -> Result<(), String> {
+        let &Vec3(vx, vy, vz) = voxel;
+
+        let (max_height, dimension, chunk_size, render_radius) = {
+            let config = self.read_resource::<WorldConfig>();
+            (
+                config.max_height,
+                config.dimension,
+                config.chunk_size,
+                config.render_radius,
+            )
+        };
+
+        if vy < 0 || vy >= max_height as i32 {
+            return Err(format!("{} is out of the world's height", vy));
+        }
+
+        let entity = self
+            .read_resource::<Players>()
+            .get(&player_id)
+            .map(|player| player.entity)
+            .ok_or_else(|| "Player not found".to_owned())?;
+
+        let dimension = dimension as f32;
+        let coords = map_voxel_to_chunk(vx, vy, vz, chunk_size);
+
+        let radius = self
+            .ecs
+            .read_storage::<ViewRadius>()
+            .get(entity)
+            .map(|radius| radius.0)
+            .unwrap_or(render_radius as i16);
+
+        if let Some(body) = self.ecs.write_storage::<RigidBody>().get_mut(entity) {
+            body.set_position(&Vec3(
+                (vx as f32 + 0.5) * dimension,
+                vy as f32 * dimension,
+                (vz as f32 + 0.5) * dimension,
+            ));
+            body.velocity = Vec3::default();
+        }
+
+        // chunks get generated here instead of waiting for the chunking system to notice
+        if let Some(curr_chunk) = self.ecs.write_storage::<CurrChunk>().get_mut(entity) {
+            curr_chunk.val = Some(coords.clone());
+            curr_chunk.changed = false;
+        }
+
+        let r = (radius as f32 / chunk_size as f32).ceil() as i16;
+        self.write_resource::<Chunks>().generate(&coords, r, false);
+
+        let mut new_message = create_of_type(MessageType::Teleport);
+        new_message.json = serde_json::json!({ "voxel": [vx, vy - 1, vz] }).to_string();
+
+        self.broadcast(&new_message, vec![player_id], vec![]);
+
+        Ok(())
+    }
+
     /// TEST:
     ///
     /// Used to test entity spawning
@@ -603,57 +1216,81 @@ impl World {
 
     /// Sync configurations to the world's JSON file
     pub fn sync_config(&mut self) {
-        let chunks = self.read_resource::<Chunks>();
+        let data = WorldData::load(&self.read_resource::<WorldConfig>(), &self.name);
 
-        let mut path = chunks.root_folder.clone();
-        path.push(WORLD_DATA_FILE);
-
-        drop(chunks);
-
-        if let Ok(file) = File::open(path) {
-            let WorldData { time, tick_speed } = serde_json::from_reader(file).unwrap();
-            let mut clock = self.write_resource::<Clock>();
+        match data {
+            Ok(Some(WorldData {
+                time, tick_speed, ..
+            })) => {
+                let mut clock = self.write_resource::<Clock>();
 
-            clock.set_time(time);
-            clock.set_tick_speed(tick_speed);
+                clock.set_time(time);
+                clock.set_tick_speed(tick_speed);
+            }
+            Ok(None) => {}
+            Err(e) => error!("World data of \"{}\" is broken: {}", self.name, e),
         }
     }
 
     /// Saves the world. Things done:
     ///
     /// 1. Saves the world configs (`time`, `tick_speed`, ...etc)
-    /// 2. Save all chunks within `chunks` to their corresponding JSON files
-    pub fn save(&self) {
-        let chunks = self.read_resource::<Chunks>();
+    /// 2. Queue all chunks changed since last save to be written in the background
+    ///
+    /// Returns the count of chunks queued to be saved.
+    pub fn save(&mut self) -> usize {
         let clock = self.read_resource::<Clock>();
+        let data = WorldData {
+            time: clock.time,
+            tick_speed: clock.tick_speed,
+            seed: Some(self.read_resource::<WorldConfig>().seed),
+        };
+        drop(clock);
+
+        let mut chunks = self.write_resource::<Chunks>();
 
         if chunks.config.save {
-            // saving world data
+            // saving world data, written to a temporary file first to never leave a broken file
             let mut root = chunks.root_folder.clone();
             root.push(WORLD_DATA_FILE);
 
-            let mut file = File::create(root.into_os_string().into_string().unwrap())
-                .expect("Could not create world config.");
+            let tmp = root.with_extension("json.tmp");
 
-            let data = WorldData {
-                time: clock.time,
-                tick_speed: clock.tick_speed,
-            };
+            let mut file = File::create(&tmp).expect("Could not create world config.");
 
             let j = serde_json::to_string(&data).unwrap();
 
             file.write_all(j.as_bytes())
                 .expect("Unable to save world data");
 
-            // saving chunks
-            chunks.save();
+            std::fs::rename(tmp, root).expect("Unable to save world data");
+
+            // saving chunks in the background
+            return chunks.save();
+        }
+
+        0
+    }
+
+    /// Create a backup of the world while it keeps running.
+    ///
+    /// All changes are flushed to disk first, then the world's folder is staged and
+    /// compressed in the background. Returns the path the backup is written to.
+    pub fn backup(&mut self) -> io::Result<PathBuf> {
+        let name = self.name.to_owned();
+
+        self.save();
 
-            // info!(
-            //     "Saving data for world \"{}\" took {:?}.",
-            //     self.name,
-            //     start.elapsed()
-            // );
+        let mut chunks = self.write_resource::<Chunks>();
+
+        if !chunks.config.save {
+            return Err(io::Error::other("World isn't saved to disk."));
         }
+
+        chunks.flush();
+
+        let backups = Backups::new(&name, &chunks.config.backups);
+        backups.create_in_background(&chunks.root_folder)
     }
 
     /// A world tick
@@ -678,6 +1315,9 @@ impl World {
             .with(ChunkingSystem, "chunking", &["peers"])
             .with(GenerationSystem, "generation", &["chunking"])
             .with(MeshingSystem, "meshing", &["generation"])
+            .with(FluidsSystem, "fluids", &["meshing"])
+            .with(BlockTicksSystem, "block_ticks", &["fluids"])
+            .with(UnloadSystem, "unload", &["block_ticks"])
             .with(SearchSystem, "search", &["peers"])
             .with(ObserveSystem, "observe", &["search"])
             .with(EntitiesSystem, "entities", &["chunking"])
@@ -692,7 +1332,7 @@ impl World {
 
         // saving the chunks
         if self.read_resource::<Clock>().tick % 8000 == 0 {
-            self.save()
+            self.save();
         }
     }
 }
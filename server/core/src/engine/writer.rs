@@ -0,0 +1,340 @@
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use hashbrown::{HashMap, HashSet};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use server_common::vec::Vec2;
+use server_utils::convert::get_chunk_name;
+
+use super::region::{ChunkData, RegionFile};
+
+/// Chunk snapshots shared between the writer thread and `ChunkWriter::pending`
+type Snapshots = Vec<(Vec2<i32>, Arc<ChunkData>)>;
+
+/// A batch of chunk snapshots to be written into region files
+#[derive(Debug)]
+struct SaveJob {
+    id: u64,
+    chunks: Snapshots,
+}
+
+/// Summary of a finished save job
+#[derive(Debug, Clone)]
+pub struct SaveReport {
+    pub id: u64,
+    pub chunks: usize,
+    pub regions: usize,
+    pub elapsed: Duration,
+    pub failed: Vec<Vec2<i32>>,
+    pub errors: Vec<String>,
+}
+
+/// Background writer persisting chunk snapshots on a dedicated thread.
+///
+/// Jobs are written one after another, so region files are never written concurrently.
+/// Snapshots stay available through `ChunkWriter::pending` until they're written, so
+/// chunks unloaded in the meantime can be loaded back without reading stale data. Snapshots
+/// that fail to be written stay pending until `ChunkWriter::retry` writes them.
+#[derive(Debug)]
+pub struct ChunkWriter {
+    job_sender: Sender<SaveJob>,
+    report_receiver: Receiver<SaveReport>,
+
+    pending: HashMap<Vec2<i32>, (u64, Arc<ChunkData>)>,
+    failed: HashSet<Vec2<i32>>,
+    in_flight: usize,
+    next_id: u64,
+}
+
+impl ChunkWriter {
+    /// Spawn the writer thread for the region files within `folder`
+    pub fn new(folder: PathBuf) -> Self {
+        let (job_sender, job_receiver) = unbounded::<SaveJob>();
+        let (report_sender, report_receiver) = unbounded();
+
+        thread::Builder::new()
+            .name("chunk-writer".to_owned())
+            .spawn(move || {
+                for job in job_receiver {
+                    if report_sender.send(write_job(&folder, job)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Unable to spawn chunk writer thread.");
+
+        Self {
+            job_sender,
+            report_receiver,
+
+            pending: HashMap::new(),
+            failed: HashSet::new(),
+            in_flight: 0,
+            next_id: 0,
+        }
+    }
+
+    /// Queue chunk snapshots to be written, returning the id of the save job.
+    ///
+    /// Returns `None` if there's nothing to write.
+    pub fn submit(&mut self, chunks: Vec<(Vec2<i32>, ChunkData)>) -> Option<u64> {
+        if chunks.is_empty() {
+            return None;
+        }
+
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let chunks = chunks
+            .into_iter()
+            .map(|(coords, data)| {
+                self.failed.remove(&coords);
+
+                let data = Arc::new(data);
+                self.pending
+                    .insert(coords.to_owned(), (id, Arc::clone(&data)));
+                (coords, data)
+            })
+            .collect();
+
+        self.job_sender
+            .send(SaveJob { id, chunks })
+            .expect("Chunk writer thread has stopped.");
+        self.in_flight += 1;
+
+        Some(id)
+    }
+
+    /// Get the latest snapshot of a chunk that's yet to be written
+    pub fn pending(&self, coords: &Vec2<i32>) -> Option<&ChunkData> {
+        self.pending.get(coords).map(|(_, data)| data.as_ref())
+    }
+
+    /// Queue again the snapshots that failed to be written and weren't replaced since,
+    /// returning the id of the save job.
+    ///
+    /// Returns `None` if there's nothing to write.
+    pub fn retry(&mut self) -> Option<u64> {
+        let pending = &self.pending;
+
+        let chunks = self
+            .failed
+            .drain()
+            .filter_map(|coords| {
+                pending
+                    .get(&coords)
+                    .map(|(_, data)| (coords, data.as_ref().to_owned()))
+            })
+            .collect();
+
+        self.submit(chunks)
+    }
+
+    /// Whether any save job is still being written
+    pub fn is_busy(&self) -> bool {
+        self.in_flight > 0
+    }
+
+    /// Collect the reports of finished save jobs without blocking
+    pub fn poll(&mut self) -> Vec<SaveReport> {
+        let mut reports = vec![];
+
+        while let Ok(report) = self.report_receiver.try_recv() {
+            self.finish(&report);
+            reports.push(report);
+        }
+
+        reports
+    }
+
+    /// Block until every queued save job is written, returning their reports
+    pub fn flush(&mut self) -> Vec<SaveReport> {
+        let mut reports = self.poll();
+
+        while self.in_flight > 0 {
+            let report = self
+                .report_receiver
+                .recv()
+                .expect("Chunk writer thread has stopped.");
+
+            self.finish(&report);
+            reports.push(report);
+        }
+
+        reports
+    }
+
+    fn finish(&mut self, report: &SaveReport) {
+        self.in_flight -= 1;
+
+        let failed = &mut self.failed;
+
+        self.pending.retain(|coords, (id, _)| {
+            if *id != report.id {
+                return true;
+            }
+
+            // the only copy of chunks unloaded since, kept to be retried
+            if report.failed.contains(coords) {
+                failed.insert(coords.to_owned());
+                return true;
+            }
+
+            false
+        });
+    }
+}
+
+/// Write a save job into its region files, grouped per region
+fn write_job(folder: &Path, job: SaveJob) -> SaveReport {
+    let start = Instant::now();
+
+    let SaveJob { id, chunks } = job;
+    let count = chunks.len();
+
+    let mut regions: HashMap<Vec2<i32>, Snapshots> = HashMap::new();
+
+    for (coords, data) in chunks {
+        regions
+            .entry(RegionFile::region_coords(&coords))
+            .or_default()
+            .push((coords, data));
+    }
+
+    let region_count = regions.len();
+
+    let mut failed = vec![];
+    let mut errors = vec![];
+
+    for (region, chunks) in regions {
+        let result = chunks
+            .iter()
+            .map(|(coords, data)| data.encode().map(|bytes| (coords.to_owned(), bytes)))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|updates| RegionFile::write_atomic(folder, &region, updates));
+
+        match result {
+            Ok(_) => {
+                // chunks migrated from legacy JSON files are now safely in their region
+                for (Vec2(cx, cz), _) in chunks.iter() {
+                    let mut legacy = folder.to_path_buf();
+                    legacy.push(format!("{}.json", get_chunk_name(*cx, *cz)));
+
+                    if legacy.exists() {
+                        let _ = fs::remove_file(legacy);
+                    }
+                }
+            }
+            Err(e) => {
+                errors.push(format!("region {:?}: {}", region, e));
+                failed.extend(chunks.into_iter().map(|(coords, _)| coords));
+            }
+        }
+    }
+
+    SaveReport {
+        id,
+        chunks: count - failed.len(),
+        regions: region_count,
+        elapsed: start.elapsed(),
+        failed,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_in_background() {
+        let mut folder = std::env::temp_dir();
+        folder.push(format!("minejs-writer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let mut legacy = folder.clone();
+        legacy.push("3_-40.json");
+        fs::write(&legacy, "{}").unwrap();
+
+        let data = ChunkData {
+            needs_propagation: false,
+            voxels: vec![1, 2, 3],
+            lights: vec![4, 5, 6],
+            height_map: vec![7],
+        };
+
+        let mut writer = ChunkWriter::new(folder.clone());
+
+        assert_eq!(writer.submit(vec![]), None);
+
+        let id = writer
+            .submit(vec![
+                (Vec2(0, 0), data.clone()),
+                (Vec2(3, -40), data.clone()),
+            ])
+            .unwrap();
+
+        assert_eq!(writer.pending(&Vec2(3, -40)), Some(&data));
+
+        let reports = writer.flush();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].id, id);
+        assert_eq!(reports[0].chunks, 2);
+        assert_eq!(reports[0].regions, 2);
+        assert!(reports[0].failed.is_empty());
+
+        assert!(!writer.is_busy());
+        assert_eq!(writer.pending(&Vec2(3, -40)), None);
+        assert!(!legacy.exists());
+
+        let mut region = RegionFile::open(&folder, &Vec2(3, -40), false)
+            .unwrap()
+            .unwrap();
+        let bytes = region.read(&Vec2(3, -40)).unwrap().unwrap();
+        assert_eq!(ChunkData::decode(&bytes).unwrap(), data);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn keep_failed_snapshots() {
+        let mut folder = std::env::temp_dir();
+        folder.push(format!("minejs-writer-failed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+
+        let data = ChunkData {
+            needs_propagation: false,
+            voxels: vec![1],
+            lights: vec![2],
+            height_map: vec![3],
+        };
+
+        // the folder doesn't exist yet, so the region can't be written
+        let mut writer = ChunkWriter::new(folder.clone());
+        writer.submit(vec![(Vec2(1, 1), data.clone())]).unwrap();
+
+        let reports = writer.flush();
+        assert_eq!(reports[0].failed, vec![Vec2(1, 1)]);
+        assert_eq!(writer.pending(&Vec2(1, 1)), Some(&data));
+
+        fs::create_dir_all(&folder).unwrap();
+
+        writer.retry().unwrap();
+        assert_eq!(writer.retry(), None);
+
+        let reports = writer.flush();
+        assert!(reports[0].failed.is_empty());
+        assert_eq!(writer.pending(&Vec2(1, 1)), None);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -9,6 +9,10 @@ use kdtree::KdTree;
 
 use serde::Deserialize;
 
+use crate::constants::LEVEL_SEED;
+
+use super::ores::OreConfig;
+
 use server_common::{
     math::smooth_interpolation,
     ndarray::{ndarray, Ndarray},
@@ -34,6 +38,17 @@ pub struct BiomeConfigs {
     pub radius_minimum: f64,
     pub river: Biome,
     pub biomes: Vec<Biome>,
+
+    /// Ores placed underground, the first listed winning where they overlap
+    #[serde(default)]
+    pub ores: Vec<OreConfig>,
+}
+
+impl BiomeConfigs {
+    /// Read the configs of `biomes.json`
+    pub fn load() -> Self {
+        serde_json::from_reader(File::open("assets/metadata/biomes.json").unwrap()).unwrap()
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -88,15 +103,17 @@ pub struct Biomes {
 
 impl Default for Biomes {
     fn default() -> Self {
-        Self::new()
+        Self::new(LEVEL_SEED)
     }
 }
 
 impl Biomes {
+    /// Biome noises are seeded from `biomes.json`, offset by the world's seed relative to
+    /// `LEVEL_SEED`, so that worlds generated before seeds were configurable keep their biomes.
+    ///
     /// https://www.desmos.com/calculator/vjrxi1kyh7
-    pub fn new() -> Self {
-        let biome_configs: BiomeConfigs =
-            serde_json::from_reader(File::open("assets/metadata/biomes.json").unwrap()).unwrap();
+    pub fn new(seed: u32) -> Self {
+        let biome_configs = BiomeConfigs::load();
 
         let BiomeConfigs {
             temperature_scale,
@@ -109,17 +126,23 @@ impl Biomes {
             ..
         } = &biome_configs;
 
+        let seed = seed.wrapping_sub(LEVEL_SEED);
+
+        let temperature_seed = temperature_seed.wrapping_add(seed);
+        let humidity_seed = humidity_seed.wrapping_add(seed);
+        let river_seed = river_seed.wrapping_add(seed);
+
         let mut new_biomes = Self {
             temperature_scale: *temperature_scale,
-            temperature_noise: Noise::new(*temperature_seed),
-            temperature_noise2: Noise::new(*temperature_seed * 2),
+            temperature_noise: Noise::new(temperature_seed),
+            temperature_noise2: Noise::new(temperature_seed.wrapping_mul(2)),
 
             humidity_scale: *humidity_scale,
-            humidity_noise: Noise::new(*humidity_seed),
-            humidity_noise2: Noise::new(*humidity_seed * 2),
+            humidity_noise: Noise::new(humidity_seed),
+            humidity_noise2: Noise::new(humidity_seed.wrapping_mul(2)),
 
             river_scale: *river_scale,
-            river_noise: Noise::new(*river_seed),
+            river_noise: Noise::new(river_seed),
 
             configs: biome_configs.clone(),
             presets: KdTree::new(2),
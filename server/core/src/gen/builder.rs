@@ -1,11 +1,15 @@
-use std::f32::consts::PI;
-
 use super::super::engine::{chunk::Chunk, registry::Registry};
 
-use super::biomes::{BiomeConfig, Biomes};
+use super::{
+    biomes::{BiomeConfig, Biomes},
+    structures::Structure,
+};
 
 use server_common::{noise::Noise, vec::Vec3};
 
+/// Farthest horizontal distance in voxels of a tree's leaves from its trunk
+const TREE_REACH: i32 = 2;
+
 /// Decoration update unit
 #[derive(Clone, Debug)]
 pub struct VoxelUpdate {
@@ -18,47 +22,41 @@ pub struct VoxelUpdate {
 pub struct Builder {
     noise: Noise,
     registry: Registry,
+    structures: Vec<Structure>,
 }
 
 impl Builder {
-    pub fn new(registry: Registry, noise: Noise) -> Self {
-        Self { noise, registry }
-    }
-
-    /// Helper function to draw a circle of blocks horizontally
-    fn draw_circle(x: i32, y: i32, z: i32, r: i32, id: u32) -> Vec<VoxelUpdate> {
-        let mut sx = 0;
-        let mut sz = 0;
-
-        let r = r as f32;
-
-        let mut updates = Vec::new();
-
-        for step in 0..360 {
-            let new_x = ((step as f32 / 360.0 * 2.0 * PI).sin() * r) as i32;
-            let new_z = ((step as f32 / 360.0 * 2.0 * PI).cos() * r) as i32;
-
-            if sx != new_x || sz != new_z {
-                updates.push(VoxelUpdate {
-                    id,
-                    voxel: Vec3(x + new_x, y, z + new_z),
-                });
-
-                sx = new_x;
-                sz = new_z;
-            }
+    /// Decorate with trees, plants and the structures named in `structures`
+    pub fn new(registry: Registry, seed: u32, structures: &[String]) -> Self {
+        let structures = Structure::load_all(&registry, seed, structures);
+
+        Self {
+            noise: Noise::new(seed),
+            registry,
+            structures,
         }
+    }
 
-        updates
+    /// Farthest horizontal distance in voxels of the decorations from the column they're
+    /// sampled on
+    pub fn reach(&self) -> i32 {
+        self.structures
+            .iter()
+            .map(|structure| structure.reach())
+            .fold(TREE_REACH, i32::max)
     }
 
     /// Sample locations within chunk to place plants down
     fn sample_plants(&self, chunk: &Chunk, biomes: &Biomes) -> Vec<Vec3<i32>> {
         let mut locations = Vec::new();
-        let Chunk { min, max, .. } = chunk;
-
-        for vx in min.0..max.0 {
-            for vz in min.2..max.2 {
+        let Chunk {
+            min_inner,
+            max_inner,
+            ..
+        } = chunk;
+
+        for vx in min_inner.0..max_inner.0 {
+            for vz in min_inner.2..max_inner.2 {
                 let vy = chunk.get_max_height(vx, vz) as i32;
 
                 let BiomeConfig { plant_scale, .. } = biomes.get_biome(vx, vz).config;
@@ -126,10 +124,14 @@ impl Builder {
     /// Sample locations within chunk to place trees down
     fn sample_trees(&self, chunk: &Chunk, biomes: &Biomes) -> Vec<Vec3<i32>> {
         let mut locations = Vec::new();
-        let Chunk { min, max, .. } = chunk;
-
-        for vx in min.0..max.0 {
-            for vz in min.2..max.2 {
+        let Chunk {
+            min_inner,
+            max_inner,
+            ..
+        } = chunk;
+
+        for vx in min_inner.0..max_inner.0 {
+            for vz in min_inner.2..max_inner.2 {
                 let vy = chunk.get_max_height(vx, vz) as i32;
                 let BiomeConfig { tree_scale, .. } = biomes.get_biome(vx, vz).config;
 
@@ -242,99 +244,50 @@ impl Builder {
         updates
     }
 
-    /// Sample locations within chunk to place lamps down
-    fn sample_lamps(&self, chunk: &Chunk) -> Vec<Vec3<i32>> {
-        let mut locations = Vec::new();
-        let Chunk { min, max, .. } = chunk;
-
-        for vx in min.0..max.0 {
-            for vz in min.2..max.2 {
-                let vy = chunk.get_max_height(vx, vz) as i32;
-                if self.noise.central_perlin(vx as f64, vz as f64, 0.02) {
-                    locations.push(Vec3(vx, vy, vz));
-                }
-            }
-        }
-
-        locations
-    }
-
-    /// Place lamps down on sampled locations
-    fn generate_lamps(&self, chunk: &Chunk) -> Vec<VoxelUpdate> {
-        let locations = self.sample_lamps(chunk);
-        let types = self.registry.get_type_map(vec!["Stone", "Color Yellow"]);
-
+    /// Place the structures of `assets/metadata/structures` on their sampled locations
+    fn generate_structures(&self, chunk: &Chunk, biomes: &Biomes) -> Vec<VoxelUpdate> {
         let mut updates = Vec::new();
-
-        for location in locations.into_iter() {
-            updates.push(VoxelUpdate {
-                voxel: location,
-                id: types["Color Yellow"],
-            })
-        }
-
-        updates
-    }
-
-    /// Sample locations within chunk to place a big stone structure down
-    fn sample_stone_structure(&self, chunk: &Chunk) -> Vec<Vec3<i32>> {
-        let mut locations = Vec::new();
-        let Chunk { min, max, .. } = chunk;
-
-        for vx in min.0..max.0 {
-            for vz in min.2..max.2 {
-                let vy = chunk.get_max_height(vx, vz) as i32;
-                if self.noise.central_perlin(vx as f64, vz as f64, 0.008) {
-                    locations.push(Vec3(vx, vy + 1, vz));
+        let Chunk {
+            min_inner,
+            max_inner,
+            ..
+        } = chunk;
+
+        for structure in self.structures.iter() {
+            for vx in min_inner.0..max_inner.0 {
+                for vz in min_inner.2..max_inner.2 {
+                    if let Some(ground) = structure.sample(chunk, vx, vz, &self.registry, biomes) {
+                        updates.append(&mut structure.place(&ground));
+                    }
                 }
             }
         }
 
-        locations
-    }
-
-    /// Place big stone structures down on sampled locations
-    fn generate_stone_structure(&self, chunk: &Chunk) -> Vec<VoxelUpdate> {
-        let locations = self.sample_stone_structure(chunk);
-        let types = self
-            .registry
-            .get_type_map(vec!["Color Yellow", "Stone Bricks"]);
-
-        let mut updates = Vec::new();
-
-        for location in locations.into_iter() {
-            let Vec3(vx, vy, vz) = location;
-
-            for i in 0..6 {
-                updates.append(&mut Builder::draw_circle(
-                    vx,
-                    vy + i,
-                    vz,
-                    3 + i % 3 - 1,
-                    types["Stone Bricks"],
-                ));
-            }
-
-            for i in 0..4 {
-                updates.push(VoxelUpdate {
-                    voxel: Vec3(vx, vy + i, vz),
-                    id: types["Color Yellow"],
-                });
-            }
-        }
-
         updates
     }
 
-    /// Returns a list of voxel updates from chunk
+    /// Returns a list of voxel updates sampled on the chunk's columns, some of which can reach
+    /// into the neighboring chunks. The chunk should have nothing but its terrain.
     pub fn build(&self, chunk: &Chunk, biomes: &Biomes) -> Vec<VoxelUpdate> {
         let mut updates = Vec::new();
 
-        // updates.append(&mut self.generate_lamps(chunk));
-        // updates.append(&mut self.generate_stone_structure(chunk));
+        updates.append(&mut self.generate_structures(chunk, biomes));
+
         // updates.append(&mut self.generate_plants(chunk, biomes));
         // updates.append(&mut self.generate_trees(chunk, biomes));
 
         updates
     }
 }
+
+#[cfg(test)]
+impl Builder {
+    /// Decorate with nothing but some structures, without reading any template
+    pub fn from_structures(registry: Registry, seed: u32, structures: Vec<Structure>) -> Self {
+        Self {
+            noise: Noise::new(seed),
+            registry,
+            structures,
+        }
+    }
+}
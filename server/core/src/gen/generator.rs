@@ -1,254 +1,24 @@
+use server_common::vec::{Vec2, Vec3};
+
 use super::super::{
-    constants::LEVEL_SEED,
+    constants::DATA_PADDING,
     engine::{chunk::Chunk, registry::Registry, world::WorldConfig},
-    gen::builder::VoxelUpdate,
 };
 
-use super::biomes::{get_biome_config, BiomeConfig, Biomes, CAVE_SCALE};
-
-use log::debug;
-use server_common::{
-    noise::{Noise, NoiseConfig},
-    vec::Vec3,
-};
+use super::{biomes::Biomes, builder::VoxelUpdate, terrain::TerrainGenerator};
 
 pub struct Generator;
 
 impl Generator {
-    /// Generate a chunk, standalone process, can be run in another thread.
+    /// Generate a chunk's terrain, standalone process, can be run in another thread.
     pub fn generate_chunk(
         chunk: &mut Chunk,
+        generator: &dyn TerrainGenerator,
         registry: &Registry,
         biomes: &Biomes,
         config: &WorldConfig,
     ) {
-        let Vec3(start_x, start_y, start_z) = chunk.min;
-        let Vec3(end_x, end_y, end_z) = chunk.max;
-
-        match config.generation.as_str() {
-            "flat" => {
-                let types = registry.get_type_map(vec!["Stone", "Stone Bricks"]);
-
-                let is_empty = true;
-
-                let flat_height = 30;
-
-                for vx in start_x..end_x {
-                    for vz in start_z..end_z {
-                        for vy in start_y..flat_height {
-                            if vx % 32 == 0 || vz % 32 == 0 {
-                                chunk.set_voxel(vx, vy, vz, types["Stone Bricks"]);
-                            } else {
-                                chunk.set_voxel(vx, vy, vz, types["Stone"]);
-                            }
-                        }
-                    }
-                }
-
-                chunk.is_empty = is_empty;
-            }
-            "hilly" => {
-                let types = registry.get_type_map(vec!["Air", "Grass Block", "Stone", "Dirt"]);
-
-                let air = types["Air"];
-                let grass_block = types["Grass Block"];
-                let stone = types["Stone"];
-                let dirt = types["Dirt"];
-
-                let is_empty = true;
-
-                let noise = Noise::new(LEVEL_SEED);
-
-                let is_solid_at = |vx: i32, vy: i32, vz: i32, biome: &BiomeConfig| {
-                    noise.octave_perlin3(
-                        vx as f64,
-                        vy as f64,
-                        vz as f64,
-                        biome.scale,
-                        NoiseConfig {
-                            octaves: biome.octaves,
-                            persistence: biome.persistence,
-                            lacunarity: biome.lacunarity,
-                            height_scale: biome.height_scale,
-                            amplifier: biome.amplifier,
-                        },
-                    ) > -0.2
-                };
-
-                let unit = (config.max_height / config.sub_chunks) as i32;
-
-                let mut pairs = vec![];
-                for i in 0..config.sub_chunks as i32 {
-                    pairs.push((
-                        Vec3(start_x, unit * i, start_z),
-                        Vec3(end_x, unit * (i + 1), end_z),
-                    ));
-                }
-
-                let updates: Vec<Vec<VoxelUpdate>> = pairs
-                    .iter()
-                    .map(|(start, end)| {
-                        let mut updates = vec![];
-
-                        let &Vec3(start_x, start_y, start_z) = start;
-                        let &Vec3(end_x, end_y, end_z) = end;
-
-                        let noise = Noise::new(LEVEL_SEED);
-
-                        for vx in start_x..end_x {
-                            for vz in start_z..end_z {
-                                let (height_offset, biome_config) =
-                                    get_biome_config(vx, vz, &noise);
-
-                                for vy in start_y..end_y {
-                                    // this is because chunks might come in with preset voxels
-                                    if chunk.get_voxel(vx, vy, vz) != 0 {
-                                        continue;
-                                    }
-
-                                    let vy_ = vy;
-                                    let vy = vy - height_offset;
-
-                                    let is_solid = is_solid_at(vx, vy, vz, &biome_config);
-
-                                    if !(is_solid) {
-                                        continue;
-                                    }
-
-                                    let is_solid_top = is_solid_at(vx, vy + 1, vz, &biome_config);
-                                    let is_solid_top2 = is_solid_at(vx, vy + 2, vz, &biome_config);
-
-                                    let vx = vx as f64;
-                                    let vy = vy as f64;
-                                    let vz = vz as f64;
-
-                                    let y_prop = vy / config.max_height as f64;
-
-                                    let mut block_id: u32;
-
-                                    if !is_solid_top && !is_solid_top2 {
-                                        block_id = grass_block;
-
-                                        if noise.fractal_octave_perlin3(
-                                            vx,
-                                            vy,
-                                            vz,
-                                            biome_config.scale,
-                                            3,
-                                        ) > 0.3
-                                        {
-                                            block_id = dirt;
-                                        }
-                                    } else {
-                                        block_id = stone;
-                                    }
-
-                                    // the y_prop is to force the caves lower in the y-axis
-                                    // the lower the scale, the bigger the caves
-                                    let cave_scale = 0.6;
-                                    if noise.simplex3(vx, vy * 0.8, vz, CAVE_SCALE * cave_scale)
-                                        * 1.0
-                                        / y_prop.powi(3)
-                                        > 0.2
-                                        && noise.ridged3(vx, vy, vz, CAVE_SCALE * cave_scale * 2.0)
-                                            > 0.4
-                                    {
-                                        block_id = air;
-                                    }
-
-                                    updates.push(VoxelUpdate {
-                                        voxel: Vec3(vx as i32, vy_ as i32, vz as i32),
-                                        id: block_id,
-                                    });
-                                }
-                            }
-                        }
-
-                        updates
-                    })
-                    .collect();
-
-                updates.iter().for_each(|updates| {
-                    updates.iter().for_each(|u| {
-                        chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
-                    })
-                });
-
-                chunk.is_empty = is_empty;
-            }
-            "biome_test" => {
-                let types =
-                    registry.get_type_map(vec!["Water", "Grass Block", "Sand", "Stone", "Dirt"]);
-
-                let is_empty = true;
-
-                let noise = Noise::new(LEVEL_SEED);
-
-                let mut sum = 0.0;
-                let mut count = 0.0;
-
-                let mut is_solid_at = |vx: i32, vy: i32, vz: i32, config: &BiomeConfig| {
-                    // vy < config.height_offset
-                    //     &&
-                    let val = noise.octave_simplex3(
-                        vx as f64,
-                        (vy - config.height_offset) as f64,
-                        vz as f64,
-                        config.scale,
-                        NoiseConfig {
-                            octaves: config.octaves,
-                            persistence: config.persistence,
-                            lacunarity: config.lacunarity,
-                            height_scale: config.height_scale,
-                            amplifier: config.amplifier,
-                        },
-                    );
-
-                    sum += val;
-                    count += 1.0;
-
-                    val > biomes.configs.solid_threshold
-                };
-
-                for vx in start_x..end_x {
-                    for vz in start_z..end_z {
-                        let biome = biomes.get_biome(vx, vz);
-
-                        let cover = *registry.get_id_by_name(&biome.blocks.cover);
-
-                        for vy in (start_y..end_y).rev() {
-                            let is_solid = is_solid_at(vx, vy, vz, &biome.config);
-
-                            if !is_solid && vy < biomes.configs.water_height {
-                                chunk.set_voxel(vx, vy, vz, types["Water"]);
-                                continue;
-                            }
-
-                            if !is_solid {
-                                continue;
-                            }
-
-                            if chunk.get_voxel(vx, vy + 2, vz) != 0 {
-                                chunk.set_voxel(vx, vy, vz, types["Stone"]);
-                                continue;
-                            }
-
-                            if chunk.get_voxel(vx, vy + 1, vz) != 0 {
-                                chunk.set_voxel(vx, vy, vz, types["Dirt"]);
-                                continue;
-                            }
-
-                            chunk.set_voxel(vx, vy, vz, cover);
-                        }
-                    }
-                }
-
-                chunk.is_empty = is_empty;
-
-                // debug!("average {:?}", sum / count);
-            }
-            _ => panic!("Generation type not found."),
-        }
+        generator.generate(chunk, registry, biomes, config);
 
         chunk.needs_terrain = false;
     }
@@ -276,8 +46,229 @@ impl Generator {
         }
     }
 
+    /// Coordinates of the chunks whose decorations can reach into the chunk at `coords`, its
+    /// padding included, in the order their decorations are placed
+    ///
+    /// Decorations reach at most `reach` voxels away from the column they're sampled on.
+    pub fn decoration_sources(
+        coords: &Vec2<i32>,
+        reach: i32,
+        config: &WorldConfig,
+    ) -> Vec<Vec2<i32>> {
+        let Vec2(cx, cz) = coords.to_owned();
+        let r = ((reach.max(0) as usize + DATA_PADDING) as f32 / config.chunk_size as f32).ceil()
+            as i32;
+
+        let mut sources = Vec::new();
+
+        for x in -r..=r {
+            for z in -r..=r {
+                sources.push(Vec2(cx + x, cz + z));
+            }
+        }
+
+        sources
+    }
+
+    /// Place the decorations sampled on a chunk's sources, keeping the ones within the chunk
+    ///
+    /// The updates should come from the terrain of the sources alone, ordered as in
+    /// `Generator::decoration_sources`, so that a chunk and its neighbors end up agreeing on
+    /// the voxels they share whichever is decorated first.
+    pub fn decorate_chunk<'a>(
+        chunk: &mut Chunk,
+        updates: impl IntoIterator<Item = &'a VoxelUpdate>,
+        check_height: impl Fn(u32) -> bool,
+        config: &WorldConfig,
+    ) {
+        let max_height = config.max_height;
+        let sub_chunks = config.sub_chunks;
+        let min = chunk.min.to_owned();
+        let max = chunk.max.to_owned();
+
+        for VoxelUpdate { voxel, id } in updates {
+            let &Vec3(vx, vy, vz) = voxel;
+
+            if vx < min.0 || vx >= max.0 || vz < min.2 || vz >= max.2 {
+                continue;
+            }
+
+            if vy < 0 || vy >= max_height as i32 {
+                continue;
+            }
+
+            let height = chunk.get_max_height(vx, vz) as i32;
+
+            chunk.set_voxel(vx, vy, vz, *id);
+            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+
+            if vy > height && check_height(*id) {
+                chunk.set_max_height(vx, vz, vy as u32);
+            }
+        }
+    }
+
     /// Logic for height map determination
     pub fn check_height(id: u32, registry: &Registry) -> bool {
         !registry.is_air(id) && !registry.is_plant(id) && !registry.is_fluid(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+
+    use std::path::Path;
+
+    use super::*;
+
+    const STONE: u32 = 1;
+    const LOG: u32 = 2;
+    const LEAVES: u32 = 3;
+    const REACH: i32 = 3;
+
+    fn config() -> WorldConfig {
+        serde_json::from_value(serde_json::json!({
+            "chunkSize": 4,
+            "dimension": 1,
+            "maxHeight": 16,
+            "maxLightLevel": 15,
+            "save": false,
+            "chunkRoot": "data",
+            "renderRadius": 2,
+            "maxLoadedChunks": 16,
+            "subChunks": 2,
+            "generation": "test",
+            "playerDimensions": [0.8, 1.8, 0.8],
+            "playerHead": 1.6,
+            "maxPerThread": 1,
+            "serverTickRate": 16
+        }))
+        .unwrap()
+    }
+
+    /// Rolling stone terrain, the same for every chunk covering a column
+    fn terrain(coords: &Vec2<i32>, config: &WorldConfig) -> Chunk {
+        let mut chunk = Chunk::new(coords.to_owned(), config, Path::new("data"));
+        let Vec3(min_x, _, min_z) = chunk.min.to_owned();
+        let Vec3(max_x, _, max_z) = chunk.max.to_owned();
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                let height = 3 + (vx + 2 * vz).rem_euclid(3);
+
+                for vy in 0..=height {
+                    chunk.set_voxel(vx, vy, vz, STONE);
+                }
+
+                chunk.set_max_height(vx, vz, height as u32);
+            }
+        }
+
+        chunk
+    }
+
+    /// Wide trees sampled on the chunk's own columns, overlapping and crossing chunk borders
+    fn build(chunk: &Chunk) -> Vec<VoxelUpdate> {
+        let mut updates = vec![];
+
+        for vx in chunk.min_inner.0..chunk.max_inner.0 {
+            for vz in chunk.min_inner.2..chunk.max_inner.2 {
+                if (vx * 7 + vz * 13).rem_euclid(5) != 0 {
+                    continue;
+                }
+
+                let vy = chunk.get_max_height(vx, vz) as i32;
+
+                for i in -REACH..=REACH {
+                    for k in -REACH..=REACH {
+                        updates.push(VoxelUpdate {
+                            voxel: Vec3(vx + i, vy + 3, vz + k),
+                            id: LEAVES,
+                        });
+                    }
+                }
+
+                for j in 1..=3 {
+                    updates.push(VoxelUpdate {
+                        voxel: Vec3(vx, vy + j, vz),
+                        id: LOG,
+                    });
+                }
+            }
+        }
+
+        updates
+    }
+
+    /// Decorate chunks one at a time in the given order, sampling the sources on their terrain
+    /// alone as `Chunks::generate` does
+    fn decorate(order: &[Vec2<i32>], config: &WorldConfig) -> HashMap<Vec2<i32>, Chunk> {
+        let mut chunks = HashMap::new();
+
+        for coords in order {
+            let updates = Generator::decoration_sources(coords, REACH, config)
+                .iter()
+                .flat_map(|source| build(&terrain(source, config)))
+                .collect::<Vec<_>>();
+
+            let mut chunk = terrain(coords, config);
+            Generator::decorate_chunk(&mut chunk, updates.iter(), |id| id != 0, config);
+
+            chunks.insert(coords.to_owned(), chunk);
+        }
+
+        chunks
+    }
+
+    #[test]
+    fn decoration_is_independent_of_order() {
+        let config = config();
+
+        let mut order = vec![];
+        for x in -1..=1 {
+            for z in -1..=1 {
+                order.push(Vec2(x, z));
+            }
+        }
+
+        let forward = decorate(&order, &config);
+
+        order.reverse();
+        order.swap(2, 6);
+        let backward = decorate(&order, &config);
+
+        for coords in order.iter() {
+            let a = &forward[coords];
+            let b = &backward[coords];
+
+            assert_eq!(a.get_voxels().data, b.get_voxels().data);
+            assert_eq!(a.get_height_map().data, b.get_height_map().data);
+            assert!(a.get_voxels().data.contains(&LEAVES));
+        }
+
+        // the padding of a chunk matches the voxels of its neighbors
+        let center = &forward[&Vec2(0, 0)];
+        for (coords, neighbor) in forward.iter() {
+            if *coords == Vec2(0, 0) {
+                continue;
+            }
+
+            for vx in center.min.0..center.max.0 {
+                for vz in center.min.2..center.max.2 {
+                    if vx < neighbor.min_inner.0
+                        || vx >= neighbor.max_inner.0
+                        || vz < neighbor.min_inner.2
+                        || vz >= neighbor.max_inner.2
+                    {
+                        continue;
+                    }
+
+                    for vy in 0..config.max_height as i32 {
+                        assert_eq!(center.get_voxel(vx, vy, vz), neighbor.get_voxel(vx, vy, vz));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -251,8 +251,6 @@ impl Lights {
             chunks.set_torch_light(vx, vy, vz, 0, color);
         }
 
-        chunks.mark_saving_from_voxel(vx, vy, vz);
-
         while !queue.is_empty() {
             let LightNode { voxel, level } = queue.pop_front().unwrap();
             let Vec3(vx, vy, vz) = voxel;
@@ -295,8 +293,6 @@ impl Lights {
                     } else {
                         chunks.set_torch_light(nvx, nvy, nvz, 0, color);
                     }
-
-                    chunks.mark_saving_from_voxel(nvx, nvy, nvz);
                 } else if nl >= level && (!is_sunlight || *oy != -1 || nl > level) {
                     fill.push_back(LightNode {
                         voxel: n_voxel,
@@ -353,8 +349,6 @@ impl Lights {
                     chunks.set_torch_light(nvx, nvy, nvz, nl, color);
                 }
 
-                chunks.mark_saving_from_voxel(nvx, nvy, nvz);
-
                 queue.push_back(LightNode {
                     voxel: n_voxel,
                     level: nl,
@@ -104,9 +104,16 @@ impl Mesher {
                             !is_transparent
                         })
                     {
-                        let texture = registry.get_texture_by_id(voxel_id);
+                        // fluids keep their levels in the stage bits, not growth stages
+                        let stage = if is_fluid {
+                            0
+                        } else {
+                            chunk.get_voxel_stage(vx, vy, vz)
+                        };
+
+                        let texture = registry.get_texture_by_stage(voxel_id, stage);
                         let texture_type = get_texture_type(texture);
-                        let uv_map = registry.get_uv_by_id(voxel_id);
+                        let uv_map = registry.get_uv_by_stage(voxel_id, stage);
 
                         if is_plant {
                             let [dx, dz] = [0, 0];
@@ -4,3 +4,6 @@ pub mod builder;
 pub mod generator;
 pub mod lights;
 pub mod mesher;
+pub mod ores;
+pub mod structures;
+pub mod terrain;
@@ -0,0 +1,247 @@
+use serde::Deserialize;
+
+use server_common::noise::Noise;
+
+use super::super::engine::registry::Registry;
+
+/// Shape of the clusters an ore is found in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OreShape {
+    /// Round pockets on the peaks of the noise
+    #[default]
+    Blob,
+    /// Long winding lines along the ridges of the noise
+    Vein,
+}
+
+/// An ore of `biomes.json`, generated within the terrain of the `hilly` and `biome_test`
+/// generators
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OreConfig {
+    pub block: String,
+
+    #[serde(default)]
+    pub shape: OreShape,
+
+    /// Names of the biomes the ore is found in, any biome if empty
+    #[serde(default)]
+    pub biomes: Vec<String>,
+
+    /// Lowest voxel height the ore is found at
+    #[serde(default)]
+    pub min_height: i32,
+
+    /// Highest voxel height the ore is found at
+    pub max_height: i32,
+
+    /// Scale of the noise shaping the clusters, larger scales making them smaller
+    pub scale: f64,
+
+    /// Minimum of the noise for a voxel to be ore, from -1 to 1, larger thresholds making the
+    /// ore rarer
+    pub threshold: f64,
+
+    /// Names of the blocks the ore replaces
+    #[serde(default = "default_replaces")]
+    pub replaces: Vec<String>,
+
+    /// Offset of the world's seed, so that ores of the same scale don't share clusters
+    #[serde(default)]
+    pub seed: u32,
+}
+
+fn default_replaces() -> Vec<String> {
+    vec!["Stone".to_owned()]
+}
+
+impl OreConfig {
+    /// Check that the ore only names blocks of the registry
+    pub fn validate(&self, registry: &Registry) -> Result<(), String> {
+        if let Some(block) = std::iter::once(&self.block)
+            .chain(self.replaces.iter())
+            .find(|name| !registry.has_name(name))
+        {
+            return Err(format!(
+                "unknown block \"{}\" in ore \"{}\"",
+                block, self.block
+            ));
+        }
+
+        if self.min_height > self.max_height {
+            return Err(format!("ore \"{}\" has an empty height range", self.block));
+        }
+
+        Ok(())
+    }
+}
+
+/// An ore resolved against the registry, ready to be placed
+#[derive(Debug)]
+pub struct Ore {
+    pub id: u32,
+    replaces: Vec<u32>,
+    biomes: Vec<String>,
+    shape: OreShape,
+    min_height: i32,
+    max_height: i32,
+    scale: f64,
+    threshold: f64,
+    noise: Noise,
+}
+
+impl Ore {
+    pub fn new(config: &OreConfig, id: u32, replaces: Vec<u32>, seed: u32) -> Self {
+        Self {
+            id,
+            replaces,
+            biomes: config.biomes.clone(),
+            shape: config.shape,
+            min_height: config.min_height,
+            max_height: config.max_height,
+            scale: config.scale,
+            threshold: config.threshold,
+            noise: Noise::new(seed.wrapping_add(config.seed)),
+        }
+    }
+
+    /// Whether the voxel of a generated block turns into the ore
+    pub fn is_at(&self, vx: i32, vy: i32, vz: i32, id: u32, biome: &str) -> bool {
+        if vy < self.min_height || vy > self.max_height || !self.replaces.contains(&id) {
+            return false;
+        }
+
+        if !self.biomes.is_empty() && !self.biomes.iter().any(|b| b == biome) {
+            return false;
+        }
+
+        let (x, y, z) = (vx as f64, vy as f64, vz as f64);
+
+        let value = match self.shape {
+            OreShape::Blob => self.noise.perlin3(x, y, z, self.scale),
+            OreShape::Vein => self.noise.ridged3(x, y, z, self.scale),
+        };
+
+        value > self.threshold
+    }
+}
+
+/// The ores of `biomes.json` of a world
+#[derive(Debug, Default)]
+pub struct Ores {
+    ores: Vec<Ore>,
+}
+
+impl Ores {
+    /// Resolve the ores against the registry, seeding their noises from the world's seed.
+    ///
+    /// Panics on unknown blocks, which `OreConfig::validate` catches at startup.
+    pub fn new(configs: &[OreConfig], registry: &Registry, seed: u32) -> Self {
+        let ores = configs
+            .iter()
+            .map(|config| {
+                let replaces = config
+                    .replaces
+                    .iter()
+                    .map(|name| *registry.get_id_by_name(name))
+                    .collect();
+
+                Ore::new(
+                    config,
+                    *registry.get_id_by_name(&config.block),
+                    replaces,
+                    seed,
+                )
+            })
+            .collect();
+
+        Self { ores }
+    }
+
+    /// Whether any of the ores is limited to some biomes, which are then needed to place them
+    pub fn needs_biomes(&self) -> bool {
+        self.ores.iter().any(|ore| !ore.biomes.is_empty())
+    }
+
+    /// The block a generated voxel ends up as, the first ore listed winning where they overlap
+    pub fn get_block(&self, vx: i32, vy: i32, vz: i32, id: u32, biome: &str) -> u32 {
+        self.ores
+            .iter()
+            .find(|ore| ore.is_at(vx, vy, vz, id, biome))
+            .map_or(id, |ore| ore.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: u32 = 3;
+    const DIRT: u32 = 1;
+
+    fn config(json: &str) -> OreConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn ores_within_their_range() {
+        let coal =
+            config(r#"{ "block": "Coal Ore", "maxHeight": 20, "scale": 0.2, "threshold": 0.3 }"#);
+        let gold = config(
+            r#"{
+                "block": "Gold Ore",
+                "shape": "vein",
+                "biomes": ["Desert"],
+                "minHeight": 5,
+                "maxHeight": 10,
+                "scale": 0.1,
+                "threshold": 0.2,
+                "seed": 7
+            }"#,
+        );
+
+        assert_eq!(coal.shape, OreShape::Blob);
+        assert_eq!(coal.replaces, vec!["Stone".to_owned()]);
+
+        let ores = Ores {
+            ores: vec![
+                Ore::new(&gold, 11, vec![STONE], 1021),
+                Ore::new(&coal, 10, vec![STONE], 1021),
+            ],
+        };
+        let again = Ore::new(&coal, 10, vec![STONE], 1021);
+        assert!(ores.needs_biomes());
+
+        let mut counts = [0; 2];
+
+        for vx in 0..32 {
+            for vy in 0..32 {
+                for vz in 0..32 {
+                    let id = ores.get_block(vx, vy, vz, STONE, "Desert");
+
+                    match id {
+                        10 => {
+                            assert!(vy <= 20);
+                            counts[0] += 1;
+                        }
+                        11 => {
+                            assert!((5..=10).contains(&vy));
+                            counts[1] += 1;
+                        }
+                        _ => assert_eq!(id, STONE),
+                    }
+
+                    assert_eq!(
+                        ores.ores[1].is_at(vx, vy, vz, STONE, "Plains"),
+                        again.is_at(vx, vy, vz, STONE, "Plains")
+                    );
+                    assert_ne!(ores.get_block(vx, vy, vz, STONE, "Plains"), 11);
+                    assert_eq!(ores.get_block(vx, vy, vz, DIRT, "Desert"), DIRT);
+                }
+            }
+        }
+
+        assert!(counts[0] > 0 && counts[1] > 0);
+    }
+}
@@ -0,0 +1,340 @@
+use log::error;
+
+use serde::Deserialize;
+
+use std::{collections::HashMap, fs::File};
+
+use server_common::{noise::Noise, vec::Vec3};
+
+use super::super::engine::{chunk::Chunk, registry::Registry};
+
+use super::{biomes::Biomes, builder::VoxelUpdate};
+
+/// Where a structure is placed while decorating chunks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Placement {
+    /// Names of the biomes the structure appears in, any biome if empty
+    #[serde(default)]
+    pub biomes: Vec<String>,
+
+    /// Scale of the noise on whose peaks the structure is placed, larger scales placing it
+    /// more often
+    pub scale: f64,
+
+    /// Minimum of the noise on a peak for the structure to be placed, from -1 to 1
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+
+    /// Names of the blocks the structure can stand on, any solid ground if empty
+    #[serde(default)]
+    pub ground: Vec<String>,
+
+    /// Offset of the world's seed, so that structures of the same scale don't share peaks
+    #[serde(default)]
+    pub seed: u32,
+}
+
+fn default_threshold() -> f64 {
+    -1.0
+}
+
+/// A structure defined in `assets/metadata/structures`, listed in `structures.json`. Worlds
+/// only place the structures named in their `structures`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureTemplate {
+    pub name: String,
+
+    /// Block names by the single character standing for them in `layers`. Any other character
+    /// leaves the voxel as generated.
+    pub palette: HashMap<String, String>,
+
+    /// Horizontal slices from the bottom up, each a list of rows along z of characters along x
+    pub layers: Vec<Vec<String>>,
+
+    /// Voxel of the layers placed right above the ground
+    #[serde(default)]
+    pub anchor: Vec3<i32>,
+
+    pub placement: Placement,
+}
+
+impl StructureTemplate {
+    /// Load the templates listed in `assets/metadata/structures.json`, none if there's no listing
+    pub fn load_all() -> Result<Vec<Self>, String> {
+        let structures_json: HashMap<String, String> =
+            match File::open("assets/metadata/structures.json") {
+                Ok(file) => serde_json::from_reader(file)
+                    .map_err(|e| format!("unable to read structures.json: {}", e))?,
+                Err(_) => return Ok(vec![]),
+            };
+
+        let mut templates = structures_json
+            .values()
+            .map(|file| {
+                let path = format!("./assets/metadata/structures/{}", file);
+                let file =
+                    File::open(&path).map_err(|_| format!("structure not found: {}", path))?;
+
+                serde_json::from_reader::<_, Self>(file)
+                    .map_err(|e| format!("unable to read structure {}: {}", path, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the same order whatever the order of the listing
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(templates)
+    }
+
+    /// Check that the template only names blocks of the registry, by single characters
+    pub fn validate(&self, registry: &Registry) -> Result<(), String> {
+        if let Some(key) = self.palette.keys().find(|key| key.chars().count() != 1) {
+            return Err(format!(
+                "structure \"{}\" has a palette key that isn't one character: \"{}\"",
+                self.name, key
+            ));
+        }
+
+        if let Some(block) = self
+            .palette
+            .values()
+            .chain(self.placement.ground.iter())
+            .find(|name| !registry.has_name(name))
+        {
+            return Err(format!(
+                "unknown block \"{}\" in structure \"{}\"",
+                block, self.name
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Blocks of the template by name, positioned relative to its anchor. Palette keys that
+    /// aren't one character are ignored, see `StructureTemplate::validate`.
+    pub fn voxels(&self) -> Vec<(Vec3<i32>, &str)> {
+        let palette = self
+            .palette
+            .iter()
+            .filter_map(|(key, name)| {
+                let mut chars = key.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some((c, name.as_str())),
+                    _ => None,
+                }
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut voxels = vec![];
+
+        for (y, layer) in self.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if let Some(&name) = palette.get(&c) {
+                        voxels.push((Vec3(x as i32, y as i32, z as i32).sub(&self.anchor), name));
+                    }
+                }
+            }
+        }
+
+        voxels
+    }
+}
+
+/// A structure template resolved against the registry, ready to be placed
+#[derive(Debug)]
+pub struct Structure {
+    pub name: String,
+    blocks: Vec<(Vec3<i32>, u32)>,
+    biomes: Vec<String>,
+    ground: Vec<u32>,
+    scale: f64,
+    threshold: f64,
+    noise: Noise,
+}
+
+impl Structure {
+    pub fn new(
+        template: &StructureTemplate,
+        registry: &Registry,
+        seed: u32,
+    ) -> Result<Self, String> {
+        template.validate(registry)?;
+
+        let blocks = template
+            .voxels()
+            .into_iter()
+            .map(|(offset, name)| (offset, *registry.get_id_by_name(name)))
+            .collect();
+
+        let Placement {
+            biomes,
+            scale,
+            threshold,
+            ground,
+            seed: offset,
+        } = template.placement.clone();
+
+        Ok(Self {
+            name: template.name.to_owned(),
+            blocks,
+            biomes,
+            ground: ground
+                .iter()
+                .map(|name| *registry.get_id_by_name(name))
+                .collect(),
+            scale,
+            threshold,
+            noise: Noise::new(seed.wrapping_add(offset)),
+        })
+    }
+
+    /// Check the templates of `structures.json` and that a world's `structures` name them
+    pub fn validate_all(registry: &Registry, names: &[String]) -> Result<(), String> {
+        let templates = StructureTemplate::load_all()?;
+
+        templates.iter().try_for_each(|t| t.validate(registry))?;
+
+        match names
+            .iter()
+            .find(|name| !templates.iter().any(|t| t.name == **name))
+        {
+            Some(name) => Err(format!("unknown structure \"{}\"", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Load the structures of `structures.json` named in `names`, logging the ones that
+    /// `Structure::validate_all` would have refused
+    pub fn load_all(registry: &Registry, seed: u32, names: &[String]) -> Vec<Self> {
+        let templates = match StructureTemplate::load_all() {
+            Ok(templates) => templates,
+            Err(e) => {
+                error!("Structures couldn't be loaded: {}", e);
+                return vec![];
+            }
+        };
+
+        templates
+            .iter()
+            .filter(|template| names.contains(&template.name))
+            .filter_map(|template| match Structure::new(template, registry, seed) {
+                Ok(structure) => Some(structure),
+                Err(e) => {
+                    error!("Skipping structure: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the structure is placed on the ground at the top of a column of the chunk,
+    /// returning the voxel of the ground
+    pub fn sample(
+        &self,
+        chunk: &Chunk,
+        vx: i32,
+        vz: i32,
+        registry: &Registry,
+        biomes: &Biomes,
+    ) -> Option<Vec3<i32>> {
+        let (x, z) = (vx as f64, vz as f64);
+
+        if !self.noise.central_perlin(x, z, self.scale)
+            || self.noise.perlin2(x, z, self.scale) < self.threshold
+        {
+            return None;
+        }
+
+        let vy = chunk.get_max_height(vx, vz) as i32;
+        let ground = chunk.get_voxel(vx, vy, vz);
+
+        if registry.is_air(ground)
+            || !registry
+                .get_block_by_id(chunk.get_voxel(vx, vy + 1, vz))
+                .is_empty
+            || (!self.ground.is_empty() && !self.ground.contains(&ground))
+        {
+            return None;
+        }
+
+        if !self.biomes.is_empty() && !self.biomes.contains(&biomes.get_biome(vx, vz).name) {
+            return None;
+        }
+
+        Some(Vec3(vx, vy, vz))
+    }
+
+    /// Farthest horizontal distance in voxels of the structure's blocks from its anchor
+    pub fn reach(&self) -> i32 {
+        self.blocks
+            .iter()
+            .map(|(offset, _)| offset.0.abs().max(offset.2.abs()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Updates placing the structure on the ground voxel
+    pub fn place(&self, ground: &Vec3<i32>) -> Vec<VoxelUpdate> {
+        let base = ground.add(&Vec3(0, 1, 0));
+
+        self.blocks
+            .iter()
+            .map(|(offset, id)| VoxelUpdate {
+                voxel: base.add(offset),
+                id: *id,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_voxels_around_anchor() {
+        let template: StructureTemplate = serde_json::from_str(
+            r#"{
+                "name": "Post",
+                "palette": { "S": "Stone", "*": "Color Yellow" },
+                "layers": [
+                    [".S.", "SSS", ".S."],
+                    [" ", " *"]
+                ],
+                "anchor": [1, 0, 1],
+                "placement": { "scale": 0.02 }
+            }"#,
+        )
+        .unwrap();
+
+        let voxels = template.voxels();
+
+        assert_eq!(voxels.len(), 6);
+        assert!(voxels.contains(&(Vec3(0, 0, 0), "Stone")));
+        assert!(voxels.contains(&(Vec3(-1, 0, 0), "Stone")));
+        assert!(voxels.contains(&(Vec3(0, 0, 1), "Stone")));
+        assert!(voxels.contains(&(Vec3(0, 1, 0), "Color Yellow")));
+        assert_eq!(template.placement.threshold, -1.0);
+
+        let registry = Registry::from_blocks(vec![
+            (3, Registry::load_block("stone.json")),
+            (1003, Registry::load_block("color-yellow.json")),
+        ]);
+
+        assert!(template.validate(&registry).is_ok());
+        assert_eq!(Structure::new(&template, &registry, 0).unwrap().reach(), 1);
+
+        let mut unknown = template.clone();
+        unknown.placement.ground = vec!["Gold Block".to_owned()];
+        assert!(unknown.validate(&registry).is_err());
+
+        let mut long_key = template.clone();
+        long_key.palette.insert("SS".to_owned(), "Stone".to_owned());
+        assert!(Structure::new(&long_key, &registry, 0).is_err());
+        assert_eq!(long_key.voxels().len(), 6);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{fmt::Debug, sync::Arc};
+
+use hashbrown::HashMap;
+
+use serde::Deserialize;
+
+use server_common::{
+    noise::{Noise, NoiseConfig},
+    vec::Vec3,
+};
+
+use super::super::engine::{chunk::Chunk, registry::Registry, world::WorldConfig};
+
+use super::{
+    biomes::{get_biome_config, BiomeConfig, BiomeConfigs, Biomes, CAVE_SCALE},
+    builder::VoxelUpdate,
+    ores::Ores,
+};
+
+/// A way of filling chunks with terrain, selected per world through `generation` in `worlds.json`.
+///
+/// Implementations are shared between generation threads, and should only depend on the
+/// chunk's coordinates and the world's seed so that chunks can be regenerated.
+pub trait TerrainGenerator: Debug + Send + Sync {
+    /// Fill a chunk with its terrain, standalone process, can be run in another thread.
+    fn generate(
+        &self,
+        chunk: &mut Chunk,
+        registry: &Registry,
+        biomes: &Biomes,
+        config: &WorldConfig,
+    );
+
+    /// Check a world's config before the world starts, so that misconfigured worlds fail at
+    /// startup instead of on generation threads.
+    fn validate(&self, _registry: &Registry, _config: &WorldConfig) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Terrain generators keyed by name, with the built-in `flat`, `hilly` and `biome_test`.
+#[derive(Clone)]
+pub struct TerrainGenerators {
+    generators: HashMap<String, Arc<dyn TerrainGenerator>>,
+}
+
+impl Default for TerrainGenerators {
+    fn default() -> Self {
+        let mut generators = Self {
+            generators: HashMap::new(),
+        };
+
+        generators.register("flat", Arc::new(FlatGenerator));
+        generators.register("hilly", Arc::new(HillyGenerator));
+        generators.register("biome_test", Arc::new(BiomeTestGenerator));
+
+        generators
+    }
+}
+
+impl TerrainGenerators {
+    /// Register a terrain generator, replacing any generator of the same name
+    pub fn register(&mut self, name: &str, generator: Arc<dyn TerrainGenerator>) {
+        self.generators.insert(name.to_owned(), generator);
+    }
+
+    /// Get a terrain generator by name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn TerrainGenerator>> {
+        self.generators.get(name).map(Arc::clone)
+    }
+
+    /// Names of all registered terrain generators, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.generators.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+/// A layer of a superflat preset
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperflatLayer {
+    pub block: String,
+    pub thickness: u32,
+}
+
+/// Lines of a block running through every layer along both horizontal axes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperflatGrid {
+    pub block: String,
+    pub spacing: i32,
+}
+
+/// Preset of the `flat` generator, set per world through `superflat` in `worlds.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuperflatConfig {
+    /// Layers stacked from the bottom of the world up
+    pub layers: Vec<SuperflatLayer>,
+
+    #[serde(default)]
+    pub grid: Option<SuperflatGrid>,
+
+    /// Cover the top layer with the block of the biome at each column
+    #[serde(default)]
+    pub biomes: bool,
+
+    /// Whether chunks get decorated by the `Builder` after generation
+    #[serde(default = "default_decorations")]
+    pub decorations: bool,
+}
+
+fn default_decorations() -> bool {
+    true
+}
+
+impl Default for SuperflatConfig {
+    fn default() -> Self {
+        Self {
+            layers: vec![SuperflatLayer {
+                block: "Stone".to_owned(),
+                thickness: 30,
+            }],
+            grid: Some(SuperflatGrid {
+                block: "Stone Bricks".to_owned(),
+                spacing: 32,
+            }),
+            biomes: false,
+            decorations: true,
+        }
+    }
+}
+
+impl SuperflatConfig {
+    /// Names of all the blocks used by this preset
+    pub fn blocks(&self) -> Vec<&str> {
+        let mut blocks = self
+            .layers
+            .iter()
+            .map(|layer| layer.block.as_str())
+            .collect::<Vec<_>>();
+
+        if let Some(grid) = &self.grid {
+            blocks.push(&grid.block);
+        }
+
+        blocks
+    }
+}
+
+/// Layers of blocks from a `SuperflatConfig` preset
+#[derive(Debug)]
+pub struct FlatGenerator;
+
+impl TerrainGenerator for FlatGenerator {
+    fn generate(
+        &self,
+        chunk: &mut Chunk,
+        registry: &Registry,
+        biomes: &Biomes,
+        config: &WorldConfig,
+    ) {
+        let Vec3(start_x, _, start_z) = chunk.min;
+        let Vec3(end_x, _, end_z) = chunk.max;
+
+        let preset = &config.superflat;
+        let types = registry.get_type_map(preset.blocks());
+
+        let max_height = config.max_height as i32;
+
+        let mut is_empty = true;
+
+        for vx in start_x..end_x {
+            for vz in start_z..end_z {
+                let on_grid = preset.grid.as_ref().is_some_and(|grid| {
+                    grid.spacing > 0 && (vx % grid.spacing == 0 || vz % grid.spacing == 0)
+                });
+
+                let mut vy = 0;
+
+                for (i, layer) in preset.layers.iter().enumerate() {
+                    let id = if on_grid {
+                        types[&preset.grid.as_ref().unwrap().block]
+                    } else if preset.biomes && i == preset.layers.len() - 1 {
+                        *registry.get_id_by_name(&biomes.get_biome(vx, vz).blocks.cover)
+                    } else {
+                        types[&layer.block]
+                    };
+
+                    for _ in 0..layer.thickness {
+                        if vy >= max_height {
+                            break;
+                        }
+
+                        if !registry.is_air(id) {
+                            chunk.set_voxel(vx, vy, vz, id);
+                            is_empty = false;
+                        }
+
+                        vy += 1;
+                    }
+                }
+            }
+        }
+
+        chunk.is_empty = is_empty;
+
+        if !preset.decorations {
+            chunk.needs_decoration = false;
+        }
+    }
+
+    fn validate(&self, registry: &Registry, config: &WorldConfig) -> Result<(), String> {
+        let preset = &config.superflat;
+
+        if let Some(block) = preset.blocks().into_iter().find(|b| !registry.has_name(b)) {
+            return Err(format!("unknown superflat block \"{}\"", block));
+        }
+
+        if preset.grid.as_ref().is_some_and(|grid| grid.spacing <= 0) {
+            return Err("superflat grid spacing must be positive".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+/// Check the ores of `biomes.json`, placed by the generators of natural terrain
+fn validate_ores(registry: &Registry) -> Result<(), String> {
+    BiomeConfigs::load()
+        .ores
+        .iter()
+        .try_for_each(|ore| ore.validate(registry))
+}
+
+/// Rolling hills with caves and ores, shaped by the biome configs of `get_biome_config`
+#[derive(Debug)]
+pub struct HillyGenerator;
+
+impl TerrainGenerator for HillyGenerator {
+    fn generate(
+        &self,
+        chunk: &mut Chunk,
+        registry: &Registry,
+        biomes: &Biomes,
+        config: &WorldConfig,
+    ) {
+        let Vec3(start_x, _, start_z) = chunk.min;
+        let Vec3(end_x, _, end_z) = chunk.max;
+
+        let types = registry.get_type_map(vec!["Air", "Grass Block", "Stone", "Dirt"]);
+
+        let air = types["Air"];
+        let grass_block = types["Grass Block"];
+        let stone = types["Stone"];
+        let dirt = types["Dirt"];
+
+        let is_empty = true;
+
+        let noise = Noise::new(config.seed);
+        let ores = Ores::new(&biomes.configs.ores, registry, config.seed);
+
+        let is_solid_at = |vx: i32, vy: i32, vz: i32, biome: &BiomeConfig| {
+            noise.octave_perlin3(
+                vx as f64,
+                vy as f64,
+                vz as f64,
+                biome.scale,
+                NoiseConfig {
+                    octaves: biome.octaves,
+                    persistence: biome.persistence,
+                    lacunarity: biome.lacunarity,
+                    height_scale: biome.height_scale,
+                    amplifier: biome.amplifier,
+                },
+            ) > -0.2
+        };
+
+        let unit = (config.max_height / config.sub_chunks) as i32;
+
+        let mut pairs = vec![];
+        for i in 0..config.sub_chunks as i32 {
+            pairs.push((
+                Vec3(start_x, unit * i, start_z),
+                Vec3(end_x, unit * (i + 1), end_z),
+            ));
+        }
+
+        let updates: Vec<Vec<VoxelUpdate>> = pairs
+            .iter()
+            .map(|(start, end)| {
+                let mut updates = vec![];
+
+                let &Vec3(start_x, start_y, start_z) = start;
+                let &Vec3(end_x, end_y, end_z) = end;
+
+                let noise = Noise::new(config.seed);
+
+                for vx in start_x..end_x {
+                    for vz in start_z..end_z {
+                        let (height_offset, biome_config) = get_biome_config(vx, vz, &noise);
+                        let biome = if ores.needs_biomes() {
+                            biomes.get_biome(vx, vz).name
+                        } else {
+                            String::new()
+                        };
+
+                        for vy in start_y..end_y {
+                            // this is because chunks might come in with preset voxels
+                            if chunk.get_voxel(vx, vy, vz) != 0 {
+                                continue;
+                            }
+
+                            let vy_ = vy;
+                            let vy = vy - height_offset;
+
+                            let is_solid = is_solid_at(vx, vy, vz, &biome_config);
+
+                            if !(is_solid) {
+                                continue;
+                            }
+
+                            let is_solid_top = is_solid_at(vx, vy + 1, vz, &biome_config);
+                            let is_solid_top2 = is_solid_at(vx, vy + 2, vz, &biome_config);
+
+                            let vx = vx as f64;
+                            let vy = vy as f64;
+                            let vz = vz as f64;
+
+                            let y_prop = vy / config.max_height as f64;
+
+                            let mut block_id: u32;
+
+                            if !is_solid_top && !is_solid_top2 {
+                                block_id = grass_block;
+
+                                if noise.fractal_octave_perlin3(vx, vy, vz, biome_config.scale, 3)
+                                    > 0.3
+                                {
+                                    block_id = dirt;
+                                }
+                            } else {
+                                block_id = stone;
+                            }
+
+                            // the y_prop is to force the caves lower in the y-axis
+                            // the lower the scale, the bigger the caves
+                            let cave_scale = 0.6;
+                            if noise.simplex3(vx, vy * 0.8, vz, CAVE_SCALE * cave_scale) * 1.0
+                                / y_prop.powi(3)
+                                > 0.2
+                                && noise.ridged3(vx, vy, vz, CAVE_SCALE * cave_scale * 2.0) > 0.4
+                            {
+                                block_id = air;
+                            }
+
+                            let (vx, vz) = (vx as i32, vz as i32);
+                            block_id = ores.get_block(vx, vy_, vz, block_id, &biome);
+
+                            updates.push(VoxelUpdate {
+                                voxel: Vec3(vx, vy_, vz),
+                                id: block_id,
+                            });
+                        }
+                    }
+                }
+
+                updates
+            })
+            .collect();
+
+        updates.iter().for_each(|updates| {
+            updates.iter().for_each(|u| {
+                chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
+            })
+        });
+
+        chunk.is_empty = is_empty;
+    }
+
+    fn validate(&self, registry: &Registry, _config: &WorldConfig) -> Result<(), String> {
+        validate_ores(registry)
+    }
+}
+
+/// Experimental terrain driven by the biomes of `biomes.json`
+#[derive(Debug)]
+pub struct BiomeTestGenerator;
+
+impl TerrainGenerator for BiomeTestGenerator {
+    fn generate(
+        &self,
+        chunk: &mut Chunk,
+        registry: &Registry,
+        biomes: &Biomes,
+        config: &WorldConfig,
+    ) {
+        let Vec3(start_x, start_y, start_z) = chunk.min;
+        let Vec3(end_x, end_y, end_z) = chunk.max;
+
+        let types = registry.get_type_map(vec!["Water", "Grass Block", "Sand", "Stone", "Dirt"]);
+
+        let is_empty = true;
+
+        let noise = Noise::new(config.seed);
+        let ores = Ores::new(&biomes.configs.ores, registry, config.seed);
+
+        let mut sum = 0.0;
+        let mut count = 0.0;
+
+        let mut is_solid_at = |vx: i32, vy: i32, vz: i32, config: &BiomeConfig| {
+            // vy < config.height_offset
+            //     &&
+            let val = noise.octave_simplex3(
+                vx as f64,
+                (vy - config.height_offset) as f64,
+                vz as f64,
+                config.scale,
+                NoiseConfig {
+                    octaves: config.octaves,
+                    persistence: config.persistence,
+                    lacunarity: config.lacunarity,
+                    height_scale: config.height_scale,
+                    amplifier: config.amplifier,
+                },
+            );
+
+            sum += val;
+            count += 1.0;
+
+            val > biomes.configs.solid_threshold
+        };
+
+        for vx in start_x..end_x {
+            for vz in start_z..end_z {
+                let biome = biomes.get_biome(vx, vz);
+
+                let cover = *registry.get_id_by_name(&biome.blocks.cover);
+
+                for vy in (start_y..end_y).rev() {
+                    let is_solid = is_solid_at(vx, vy, vz, &biome.config);
+
+                    if !is_solid && vy < biomes.configs.water_height {
+                        chunk.set_voxel(vx, vy, vz, types["Water"]);
+                        continue;
+                    }
+
+                    if !is_solid {
+                        continue;
+                    }
+
+                    if chunk.get_voxel(vx, vy + 2, vz) != 0 {
+                        let id = ores.get_block(vx, vy, vz, types["Stone"], &biome.name);
+                        chunk.set_voxel(vx, vy, vz, id);
+                        continue;
+                    }
+
+                    if chunk.get_voxel(vx, vy + 1, vz) != 0 {
+                        chunk.set_voxel(vx, vy, vz, types["Dirt"]);
+                        continue;
+                    }
+
+                    chunk.set_voxel(vx, vy, vz, cover);
+                }
+            }
+        }
+
+        chunk.is_empty = is_empty;
+
+        // debug!("average {:?}", sum / count);
+    }
+
+    fn validate(&self, registry: &Registry, _config: &WorldConfig) -> Result<(), String> {
+        validate_ores(registry)
+    }
+}
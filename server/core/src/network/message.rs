@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use actix::prelude::*;
 
+use std::sync::Arc;
+
 use crate::engine::entities::EntityPrototypes;
+use crate::gen::terrain::TerrainGenerator;
 
 use super::super::engine::registry::{Blocks, Ranges};
 
@@ -58,6 +61,25 @@ pub struct PlayerMessage {
 #[rtype(result = "()")]
 pub struct Noop;
 
+/* -------------------------------------------------------------------------- */
+/*                               Setup Messages                               */
+/* -------------------------------------------------------------------------- */
+
+/// Register a terrain generator, selectable through `generation` in `worlds.json`.
+///
+/// Must be sent before `StartWorlds` to be used by any world.
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct RegisterGenerator {
+    pub name: String,
+    pub generator: Arc<dyn TerrainGenerator>,
+}
+
+/// Load all the worlds from `worlds.json` and start ticking them
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct StartWorlds;
+
 /* -------------------------------------------------------------------------- */
 /*                              Routing Messages                              */
 /* -------------------------------------------------------------------------- */
@@ -85,6 +107,7 @@ pub struct FullWorldData {
     pub name: String,
     pub save: bool,
     pub tick_speed: f32,
+    pub seed: u32,
     pub render_radius: usize,
     pub sub_chunks: u32,
     pub blocks: Blocks,
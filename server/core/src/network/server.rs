@@ -2,23 +2,27 @@ use actix::prelude::*;
 use actix_broker::BrokerSubscribe;
 
 use hashbrown::HashMap;
+use log::warn;
 use std::time::Duration;
 
 use crate::engine::config::Configs;
 use crate::engine::entities::Entities;
 use crate::engine::world::{WorldConfig, WorldMeta};
+use crate::gen::{structures::Structure, terrain::TerrainGenerators};
 
 use super::super::engine::{chunks::Chunks, clock::Clock, players::Players, world::World};
 
 use super::message::{
     FullWorldData, GetWorld, JoinWorld, LeaveWorld, ListWorldNames, ListWorlds, Noop,
-    PlayerMessage, SimpleWorldData,
+    PlayerMessage, RegisterGenerator, SimpleWorldData, StartWorlds,
 };
 use super::models::{messages, messages::message::Type as MessageType};
 
 #[derive(Default)]
 pub struct WsServer {
     worlds: HashMap<String, World>,
+    generators: TerrainGenerators,
+    started: bool,
 }
 
 impl WsServer {
@@ -27,8 +31,33 @@ impl WsServer {
         let mut worlds: HashMap<String, World> = HashMap::new();
         let (configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
 
-        configs.into_iter().for_each(|(_, (meta, config))| {
-            let mut new_world = World::new(meta, config, registry.to_owned());
+        // check every world's generator before loading any of them
+        let generators = configs
+            .iter()
+            .map(|(name, (_, config))| {
+                let generator = self.generators.get(&config.generation).unwrap_or_else(|| {
+                    panic!(
+                        "Unknown generation \"{}\" for world \"{}\", available generators: {}.",
+                        config.generation,
+                        name,
+                        self.generators.names().join(", ")
+                    )
+                });
+
+                if let Err(e) = generator
+                    .validate(&registry, config)
+                    .and_then(|_| Structure::validate_all(&registry, &config.structures))
+                {
+                    panic!("Invalid config for world \"{}\": {}.", name, e);
+                }
+
+                (name.to_owned(), generator)
+            })
+            .collect::<HashMap<_, _>>();
+
+        configs.into_iter().for_each(|(name, (meta, config))| {
+            let generator = generators[&name].clone();
+            let mut new_world = World::new(meta, config, registry.to_owned(), generator);
             new_world.preload();
             worlds.insert(new_world.name.to_owned(), new_world);
         });
@@ -184,6 +213,7 @@ impl Handler<GetWorld> for WsServer {
             sub_chunks: config.sub_chunks,
             tick_speed: clock.tick_speed,
             time: clock.time,
+            seed: config.seed,
             blocks: registry.blocks.to_owned(),
             ranges: registry.ranges.to_owned(),
             entities: entities.get_all(),
@@ -194,11 +224,36 @@ impl Handler<GetWorld> for WsServer {
     }
 }
 
-impl SystemService for WsServer {
-    fn service_started(&mut self, ctx: &mut Context<Self>) {
+impl Handler<RegisterGenerator> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: RegisterGenerator, _ctx: &mut Self::Context) {
+        if self.started {
+            warn!(
+                "Generator \"{}\" registered after worlds started, it will not be used.",
+                msg.name
+            );
+        }
+
+        self.generators.register(&msg.name, msg.generator);
+    }
+}
+
+impl Handler<StartWorlds> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: StartWorlds, ctx: &mut Self::Context) {
+        if self.started {
+            return;
+        }
+
+        self.started = true;
+
         self.load_worlds();
         self.start_worlds(ctx);
     }
 }
 
+impl SystemService for WsServer {}
+
 impl Supervised for WsServer {}
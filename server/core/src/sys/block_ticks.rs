@@ -0,0 +1,87 @@
+use specs::{ReadExpect, System, WriteExpect};
+
+use crate::{
+    engine::{
+        chunks::Chunks,
+        clock::Clock,
+        fluids::Fluids,
+        ticks::{BlockBehaviors, BlockTicks, ScheduledTick, TickKind},
+        world::MessagesQueue,
+    },
+    network::models::{create_message, messages, MessageComponents, MessageType},
+};
+
+pub struct BlockTicksSystem;
+
+impl<'a> System<'a> for BlockTicksSystem {
+    type SystemData = (
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, BlockBehaviors>,
+        WriteExpect<'a, BlockTicks>,
+        WriteExpect<'a, Fluids>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (clock, behaviors, mut ticks, mut fluids, mut chunks, mut messages) = data;
+
+        let mut ticked = vec![];
+
+        // scheduled ticks only fire if the block is still there
+        for ScheduledTick { voxel, id } in ticks.due(clock.tick) {
+            if chunks.get_voxel_by_voxel(voxel.0, voxel.1, voxel.2) == id {
+                ticked.push((voxel, id, TickKind::Scheduled));
+            }
+        }
+
+        if ticks.is_random_tick(clock.tick) && behaviors.has_random_ticks() {
+            for voxel in ticks.random_voxels(&chunks) {
+                let id = chunks.get_voxel_by_voxel(voxel.0, voxel.1, voxel.2);
+
+                if behaviors.get(id).is_some_and(|b| b.random_ticks()) {
+                    ticked.push((voxel, id, TickKind::Random));
+                }
+            }
+        }
+
+        let mut changes = vec![];
+
+        for (voxel, id, kind) in ticked {
+            if let Some(behavior) = behaviors.get(id) {
+                changes.append(&mut behavior.tick(&chunks, &mut ticks, &voxel, kind));
+            }
+        }
+
+        let mut results = vec![];
+
+        for change in changes {
+            if !chunks.simulate_update(&change.voxel, change.id, change.stage) {
+                continue;
+            }
+
+            fluids.schedule_around(&change.voxel, clock.tick);
+
+            results.push(messages::Update {
+                vx: change.voxel.0,
+                vy: change.voxel.1,
+                vz: change.voxel.2,
+                r#type: change.id,
+                rotation: 0,
+                y_rotation: 0,
+            });
+        }
+
+        if results.is_empty() {
+            return;
+        }
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.chunks = Some(chunks.drain_cache());
+
+        let mut new_message = create_message(components);
+        new_message.updates = results;
+
+        messages.push((new_message, None, None, None));
+    }
+}
@@ -0,0 +1,68 @@
+use specs::{ReadExpect, System, WriteExpect};
+
+use crate::{
+    engine::{
+        chunks::Chunks,
+        clock::Clock,
+        fluids::{FluidUpdate, Fluids},
+        world::MessagesQueue,
+    },
+    network::models::{create_message, messages, MessageComponents, MessageType},
+};
+
+pub struct FluidsSystem;
+
+impl<'a> System<'a> for FluidsSystem {
+    type SystemData = (
+        ReadExpect<'a, Clock>,
+        WriteExpect<'a, Fluids>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (clock, mut fluids, mut chunks, mut messages) = data;
+
+        let due = fluids.due(clock.tick);
+
+        if due.is_empty() {
+            return;
+        }
+
+        let mut results = vec![];
+
+        for voxel in due {
+            let FluidUpdate { voxel, id, stage } = match Fluids::flow(&chunks, &voxel) {
+                Some(update) => update,
+                None => continue,
+            };
+
+            if !chunks.simulate_update(&voxel, id, stage) {
+                continue;
+            }
+
+            fluids.schedule_around(&voxel, clock.tick);
+
+            results.push(messages::Update {
+                vx: voxel.0,
+                vy: voxel.1,
+                vz: voxel.2,
+                r#type: id,
+                rotation: 0,
+                y_rotation: 0,
+            });
+        }
+
+        if results.is_empty() {
+            return;
+        }
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.chunks = Some(chunks.drain_cache());
+
+        let mut new_message = create_message(components);
+        new_message.updates = results;
+
+        messages.push((new_message, None, None, None));
+    }
+}
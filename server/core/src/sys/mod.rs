@@ -1,6 +1,8 @@
+mod block_ticks;
 mod broadcast;
 mod chunking;
 mod entities;
+mod fluids;
 mod generation;
 mod meshing;
 mod observe;
@@ -8,11 +10,14 @@ mod pathfind;
 mod peers;
 mod physics;
 mod search;
+mod unload;
 mod walk_towards;
 
+pub use block_ticks::BlockTicksSystem;
 pub use broadcast::BroadcastSystem;
 pub use chunking::ChunkingSystem;
 pub use entities::EntitiesSystem;
+pub use fluids::FluidsSystem;
 pub use generation::GenerationSystem;
 pub use meshing::MeshingSystem;
 pub use observe::ObserveSystem;
@@ -20,4 +25,5 @@ pub use pathfind::PathFindSystem;
 pub use peers::PeersSystem;
 pub use physics::PhysicsSystem;
 pub use search::SearchSystem;
+pub use unload::UnloadSystem;
 pub use walk_towards::WalkTowardsSystem;
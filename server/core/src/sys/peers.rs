@@ -70,6 +70,9 @@ impl<'a> System<'a> for PeersSystem {
                     ..
                 } = update;
 
+                // roles are granted by name, so players keep the name they joined with
+                let new_name = name.0.clone().unwrap_or(new_name);
+
                 peers_update.insert(
                     id.0,
                     PeerProtocol {
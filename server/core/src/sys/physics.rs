@@ -20,8 +20,8 @@ impl<'a> System<'a> for PhysicsSystem {
         let (core, clock, chunks, mut bodies) = data;
 
         let test_solid =
-            |x: i32, y: i32, z: i32| -> bool { !chunks.get_walkable_by_voxel(x, y, z) };
-        let test_fluid = |_, _, _| false;
+            |x: i32, y: i32, z: i32| -> bool { !chunks.get_passable_by_voxel(x, y, z) };
+        let test_fluid = |x: i32, y: i32, z: i32| -> bool { chunks.get_fluidity_by_voxel(x, y, z) };
 
         for body in (&mut bodies).join() {
             core.iterate_body(body, clock.delta_secs(), &test_solid, &test_fluid);
@@ -0,0 +1,49 @@
+use hashbrown::HashSet;
+
+use specs::{ReadStorage, System, WriteExpect};
+
+use server_common::vec::Vec2;
+
+use crate::{
+    comp::{curr_chunk::CurrChunk, id::Id, view_radius::ViewRadius},
+    engine::chunks::Chunks,
+};
+
+pub struct UnloadSystem;
+
+impl<'a> System<'a> for UnloadSystem {
+    type SystemData = (
+        WriteExpect<'a, Chunks>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, ViewRadius>,
+        ReadStorage<'a, CurrChunk>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (mut chunks, ids, radiuses, curr_chunks) = data;
+
+        if chunks.len() <= chunks.config.max_loaded_chunks {
+            return;
+        }
+
+        let chunk_size = chunks.config.chunk_size;
+        let mut pinned = HashSet::new();
+
+        for (radius, curr_chunk, _) in (&radiuses, &curr_chunks, &ids).join() {
+            if let Some(Vec2(cx, cz)) = &curr_chunk.val {
+                // same as the terrain radius of `Chunks::generate`
+                let r = (radius.0 as f32 / chunk_size as f32).ceil() as i32 + 3;
+
+                for x in -r..=r {
+                    for z in -r..=r {
+                        pinned.insert(Vec2(cx + x, cz + z));
+                    }
+                }
+            }
+        }
+
+        chunks.unload(&pinned);
+    }
+}
@@ -44,6 +44,7 @@ impl<'a> System<'a> for WalkTowardsSystem {
                         } else {
                             // arrived at target, don't move.
                             brain.stop();
+                            brain.swim(body);
                             return;
                         }
                     }
@@ -72,6 +73,7 @@ impl<'a> System<'a> for WalkTowardsSystem {
                     brain.operate(&target, body, delta);
                 } else {
                     brain.stop();
+                    brain.swim(body);
                 }
             });
     }
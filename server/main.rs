@@ -55,8 +55,9 @@ async fn main() -> std::io::Result<()> {
 
     info!("🚀  MineJS running on http://{}", &addr);
 
-    // Wake up the sever
-    WsServer::from_registry().do_send(message::Noop);
+    // Wake up the sever, custom terrain generators should be registered here through
+    // `message::RegisterGenerator` before the worlds start
+    WsServer::from_registry().do_send(message::StartWorlds);
 
     srv.run().await
 }
@@ -0,0 +1,72 @@
+use std::{env, path::PathBuf, process};
+
+use server_core::engine::{backup::Backups, config::Configs};
+
+const USAGE: &str = "Usage: backup <create|list|restore> <world> [backup file]";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 2 {
+        println!("{}", USAGE);
+        process::exit(1);
+    }
+
+    let (configs, _) = Configs::load_worlds("assets/metadata/worlds.json");
+
+    let (_, config) = configs.get(&args[1]).unwrap_or_else(|| {
+        println!("World not found: {}", args[1]);
+        process::exit(1);
+    });
+
+    let backups = Backups::new(&args[1], &config.backups);
+
+    let mut world_folder = PathBuf::from(&config.chunk_root);
+    world_folder.push(&args[1]);
+
+    match args[0].as_str() {
+        "create" => {
+            println!(
+                "Make sure the server isn't running world \"{}\".\n",
+                args[1]
+            );
+
+            let path = backups
+                .create(&world_folder)
+                .expect("Unable to create backup.");
+            println!("Backup created at {:?}", path);
+        }
+        "list" => {
+            for backup in backups.list().expect("Unable to list backups.") {
+                println!("{}", backup.display());
+            }
+        }
+        "restore" => {
+            if args.len() < 3 {
+                println!("{}", USAGE);
+                process::exit(1);
+            }
+
+            let mut backup = PathBuf::from(&args[2]);
+
+            // allow passing only the file name of a listed backup
+            if !backup.exists() {
+                backup = backups.folder.join(&args[2]);
+            }
+
+            let previous = backups
+                .restore(&backup, &world_folder)
+                .expect("Unable to restore backup.");
+
+            println!("Restored {:?} into {:?}", backup, world_folder);
+
+            if let Some(previous) = previous {
+                println!("Previous world data kept at {:?}", previous);
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            process::exit(1);
+        }
+    }
+}
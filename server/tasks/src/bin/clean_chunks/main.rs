@@ -19,7 +19,5 @@ fn main() {
                 }
             }
         }
-
-        chunk.save();
     });
 }
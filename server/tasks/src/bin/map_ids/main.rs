@@ -48,8 +48,6 @@ fn main() {
                     }
                 }
             }
-
-            chunk.save();
         });
 
         let j = serde_json::to_string(&fixes).unwrap();
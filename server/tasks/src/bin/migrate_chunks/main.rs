@@ -0,0 +1,11 @@
+use server_tasks::loop_through_chunks;
+
+fn main() {
+    println!("Migrating JSON chunk files in ./data into region files...\n");
+
+    loop_through_chunks(&|chunk, _| {
+        if chunk.legacy_file().exists() {
+            chunk.mark_changed();
+        }
+    });
+}
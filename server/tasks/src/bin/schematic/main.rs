@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process,
+};
+
+use server_common::vec::{Vec2, Vec3};
+use server_core::{
+    constants::SCHEMATICS_FOLDER,
+    engine::{
+        chunk::Chunk,
+        config::Configs,
+        edits::{Cuboid, VoxelEdit},
+        registry::Registry,
+        schematic::{Schematic, Transform},
+        world::WorldConfig,
+    },
+    gen::generator::Generator,
+};
+use server_utils::convert::map_voxel_to_chunk;
+
+const USAGE: &str = "Usage: schematic export <world> <name> <x1> <y1> <z1> <x2> <y2> <z2>
+       schematic import <world> <name> <x> <y> <z> [degrees] [x|z|xz]";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 6 {
+        exit(USAGE);
+    }
+
+    let (configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
+
+    let (_, config) = configs
+        .get(&args[1])
+        .unwrap_or_else(|| exit(&format!("World not found: {}", args[1])));
+
+    let mut schematics = PathBuf::from(&config.chunk_root);
+    schematics.push(SCHEMATICS_FOLDER);
+
+    let path = Schematic::path_of(&schematics, &args[2]).unwrap_or_else(|| {
+        exit("Schematic names can only have letters, digits, - and _");
+    });
+
+    let mut folder = PathBuf::from(&config.chunk_root);
+    folder.push(&args[1]);
+    folder.push("chunks");
+
+    println!(
+        "Make sure the server isn't running world \"{}\".\n",
+        args[1]
+    );
+
+    let coordinate = |index: usize| -> i32 {
+        args[index]
+            .parse()
+            .unwrap_or_else(|_| exit(&format!("Not a coordinate: {}", args[index])))
+    };
+
+    match args[0].as_str() {
+        "export" if args.len() == 9 => {
+            let cuboid = Cuboid::new(
+                &Vec3(coordinate(3), coordinate(4), coordinate(5)),
+                &Vec3(coordinate(6), coordinate(7), coordinate(8)),
+            )
+            .clamp_height(config.max_height)
+            .unwrap_or_else(|| exit("The area is out of the world's height"));
+
+            export(&cuboid, &path, config, &registry, &folder);
+        }
+        "import" if args.len() <= 8 => {
+            let origin = Vec3(coordinate(3), coordinate(4), coordinate(5));
+
+            let degrees = args.get(6).map(|_| coordinate(6));
+            let transform = Transform::parse(degrees, args.get(7).map(|m| m.as_str()))
+                .unwrap_or_else(|e| exit(&e));
+
+            import(&origin, &transform, &path, config, &registry, &folder);
+        }
+        _ => exit(USAGE),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    println!("{}", message);
+    process::exit(1);
+}
+
+fn export(cuboid: &Cuboid, path: &Path, config: &WorldConfig, registry: &Registry, folder: &Path) {
+    let coords = cuboid.chunks(config.chunk_size);
+    let chunks = load_chunks(&coords, config, folder);
+
+    if let Some(missing) = coords.iter().find(|c| !chunks.contains_key(c)) {
+        exit(&format!("Chunk {:?} hasn't been generated yet", missing));
+    }
+
+    let voxels = cuboid
+        .voxels()
+        .map(|voxel| {
+            let Vec3(vx, vy, vz) = voxel;
+            let chunk = &chunks[&map_voxel_to_chunk(vx, vy, vz, config.chunk_size)];
+            let raw = chunk.get_raw_voxel(vx, vy, vz);
+
+            VoxelEdit::from_raw(voxel, raw)
+        })
+        .collect::<Vec<_>>();
+
+    Schematic::from_voxels(cuboid, &voxels, registry)
+        .save(path)
+        .expect("Unable to save schematic.");
+
+    println!("Exported {} blocks to {:?}", voxels.len(), path);
+}
+
+fn import(
+    origin: &Vec3<i32>,
+    transform: &Transform,
+    path: &Path,
+    config: &WorldConfig,
+    registry: &Registry,
+    folder: &Path,
+) {
+    let schematic = Schematic::load(path).expect("Unable to read schematic.");
+    let edits = schematic
+        .to_edits(origin, transform, registry)
+        .unwrap_or_else(|e| exit(&e));
+
+    let size = transform.size(&schematic.size);
+    let cuboid = Cuboid::new(origin, &origin.add(&size.sub(&Vec3(1, 1, 1))));
+
+    if cuboid.min.1 < 0 || cuboid.max.1 >= config.max_height as i32 {
+        exit("The schematic doesn't fit in the world's height");
+    }
+
+    let coords = cuboid.chunks(config.chunk_size);
+
+    // neighbors keep a padding of the voxels at the chunk's edges
+    let padded = Cuboid::new(
+        &cuboid
+            .min
+            .sub(&Vec3(config.chunk_size as i32, 0, config.chunk_size as i32)),
+        &cuboid
+            .max
+            .add(&Vec3(config.chunk_size as i32, 0, config.chunk_size as i32)),
+    );
+    let mut chunks = load_chunks(&padded.chunks(config.chunk_size), config, folder);
+
+    if let Some(missing) = coords.iter().find(|c| !chunks.contains_key(c)) {
+        exit(&format!("Chunk {:?} hasn't been generated yet", missing));
+    }
+
+    for edit in edits.iter() {
+        let Vec3(vx, vy, vz) = edit.voxel;
+        let raw = edit.to_raw();
+
+        for chunk in chunks.values_mut() {
+            if vx >= chunk.min.0 && vx < chunk.max.0 && vz >= chunk.min.2 && vz < chunk.max.2 {
+                chunk.set_raw_voxel(vx, vy, vz, raw);
+            }
+        }
+    }
+
+    let mut changed = 0;
+
+    for chunk in chunks.values_mut() {
+        if !chunk.needs_saving() {
+            continue;
+        }
+
+        Generator::generate_chunk_height_map(chunk, registry, config);
+
+        // lights are calculated again once the server loads the chunk
+        chunk.needs_propagation = true;
+
+        changed += 1;
+    }
+
+    Chunk::save_all(chunks.values().filter(|chunk| chunk.needs_saving()))
+        .expect("Unable to save chunks.");
+
+    println!(
+        "Imported {} blocks at {:?}, changing {} chunks",
+        edits.len(),
+        origin,
+        changed
+    );
+}
+
+/// Load the saved chunks at `coords`, leaving out the ones never generated
+fn load_chunks(
+    coords: &[Vec2<i32>],
+    config: &WorldConfig,
+    folder: &Path,
+) -> HashMap<Vec2<i32>, Chunk> {
+    coords
+        .iter()
+        .filter_map(|coords| {
+            let mut chunk = Chunk::new(coords.to_owned(), config, folder);
+
+            if let Err(e) = chunk.try_load() {
+                exit(&format!("Unable to load chunk {:?}: {}", coords, e));
+            }
+
+            if chunk.needs_terrain {
+                None
+            } else {
+                Some((coords.to_owned(), chunk))
+            }
+        })
+        .collect()
+}
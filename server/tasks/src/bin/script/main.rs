@@ -46,7 +46,7 @@ fn main() {
     let mut sum = 0.0;
     let side = 500;
 
-    let biomes = Biomes::new();
+    let biomes = Biomes::default();
 
     let mut min = 100.0;
     let mut max = -100.0;
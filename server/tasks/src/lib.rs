@@ -1,14 +1,20 @@
 use std::{
+    collections::HashSet,
     fs::{self, File},
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
-use server_core::engine::{chunk::Chunk, config::Configs, registry::Registry};
+use server_common::vec::Vec2;
+use server_core::engine::{chunk::Chunk, config::Configs, region::RegionFile, registry::Registry};
 use server_utils::convert::parse_chunk_name;
 
 use indicatif::{ProgressBar, ProgressStyle};
 
+/// Changed chunks saved together, as each save rewrites the whole regions of its chunks
+const SAVE_BATCH: usize = 64;
+
+/// Run `func` on every saved chunk of every saved world, then save the chunks it changed
 pub fn loop_through_chunks(func: &dyn Fn(&mut Chunk, &Registry)) {
     let (configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
 
@@ -18,41 +24,86 @@ pub fn loop_through_chunks(func: &dyn Fn(&mut Chunk, &Registry)) {
 
             println!("Processing world: {}", name);
 
-            let save_dir = fs::read_dir(&path).unwrap();
-            let pb = ProgressBar::new(save_dir.count() as u64);
+            let mut chunks = list_chunks(&path);
+            // chunks of the same region next to each other, to rewrite each region fewer times
+            chunks.sort_by_key(|coords| {
+                let Vec2(rx, rz) = RegionFile::region_coords(coords);
+                (rx, rz, coords.0, coords.1)
+            });
+
+            let pb = ProgressBar::new(chunks.len() as u64);
 
             pb.set_style(ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes}")
                 .progress_chars("#>-"));
 
-            let save_dir = fs::read_dir(&path).unwrap();
+            let mut changed = vec![];
 
-            for chunk_file in save_dir.flatten() {
-                let chunk_path = chunk_file.path();
-                if let Ok(chunk_data) = File::open(&chunk_path) {
-                    let results: Result<serde_json::Value, serde_json::Error> =
-                        serde_json::from_reader(chunk_data);
-                    if results.is_err() {
-                        // remove chunk file
-                        fs::remove_file(&chunk_path).unwrap();
+            for coords in chunks {
+                let mut chunk = Chunk::new(coords.to_owned(), &config, &path);
 
-                        continue;
-                    }
-                }
+                if let Err(e) = chunk.try_load() {
+                    pb.println(format!("Skipping chunk {:?}: {}", coords, e));
+                    pb.inc(1);
 
-                let chunk_file = chunk_file.file_name().into_string().unwrap();
-                let chunk_file = &chunk_file[0..chunk_file.len() - 5];
+                    continue;
+                }
 
-                let coords = parse_chunk_name(chunk_file);
+                func(&mut chunk, &registry);
 
-                let mut chunk = Chunk::new(coords.to_owned(), &config, &path);
+                if chunk.needs_saving() {
+                    changed.push(chunk);
+                }
 
-                func(&mut chunk, &registry);
+                if changed.len() >= SAVE_BATCH {
+                    Chunk::save_all(changed.iter()).expect("Unable to save chunks.");
+                    changed.clear();
+                }
 
                 pb.inc(1);
             }
 
+            Chunk::save_all(changed.iter()).expect("Unable to save chunks.");
+
             pb.finish();
         }
     });
 }
+
+/// List the coordinates of all chunks stored in region files and in legacy
+/// JSON chunk files, removing legacy files that are corrupted.
+fn list_chunks(path: &Path) -> Vec<Vec2<i32>> {
+    let mut chunks = HashSet::new();
+
+    for region in RegionFile::list(path).unwrap() {
+        if let Ok(Some(region)) = RegionFile::open_region(path, &region, false) {
+            chunks.extend(region.chunks());
+        }
+    }
+
+    for chunk_file in fs::read_dir(path).unwrap().flatten() {
+        let chunk_path = chunk_file.path();
+
+        if chunk_path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        if let Ok(chunk_data) = File::open(&chunk_path) {
+            let results: Result<serde_json::Value, serde_json::Error> =
+                serde_json::from_reader(chunk_data);
+            if results.is_err() {
+                // remove chunk file
+                fs::remove_file(&chunk_path).unwrap();
+
+                continue;
+            }
+        }
+
+        let chunk_file = chunk_file.file_name().into_string().unwrap();
+        let chunk_file = &chunk_file[0..chunk_file.len() - 5];
+
+        chunks.insert(parse_chunk_name(chunk_file));
+    }
+
+    chunks.into_iter().collect()
+}